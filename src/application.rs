@@ -1 +1,471 @@
+pub mod batch;
+pub mod config;
 
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, Seek, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        category::Category,
+        cue::sentence_cues,
+        effect::Effects,
+        humanize::Performance,
+        instrument::Instrument,
+        renderer::{total_frames, Renderer},
+        text2track::Text2Track,
+        text_analyzer::TextAnalyzer,
+    },
+    error::Result,
+    infrastructure::{
+        export_flac::encode_flac,
+        export_midi::{encode_midi, MidiTrack},
+        export_wav::{write_wav_with_metadata, BitDepth, WavFormat, WavMetadata},
+    },
+};
+
+use config::GenerationConfig;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+pub type TextRenderer = Effects<Renderer<Box<dyn Instrument + Send>>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Wav,
+    Midi,
+    Flac,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Midi => "mid",
+            OutputFormat::Flac => "flac",
+        }
+    }
+}
+
+pub fn text_to_performance(text: &str, config: &GenerationConfig) -> Performance {
+    let mut text2track = Text2Track::new(TextAnalyzer::new(text.to_string()));
+    if let Some(style) = config.style {
+        text2track = text2track.with_track_type(style);
+    }
+    let original = text2track.key();
+    let track = config.arrange(&text2track.generate_track(), original);
+    match &config.humanize {
+        Some(humanize) => humanize.perform(&track, &config.key(original), config.seed(text)),
+        None => Performance {
+            track,
+            velocities: None,
+        },
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEXT_TO_SAMPLES_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+pub fn text_to_samples(text: &str, config: &GenerationConfig) -> Result<Vec<f64>> {
+    #[cfg(test)]
+    TEXT_TO_SAMPLES_CALLS.with(|calls| calls.set(calls.get() + 1));
+    Ok(text_to_renderer(text, config)?.collect())
+}
+
+pub fn text_to_renderer(text: &str, config: &GenerationConfig) -> Result<TextRenderer> {
+    performance_to_renderer(text, text_to_performance(text, config), config)
+}
+
+fn category(text: &str) -> Category {
+    Category::detect(&TextAnalyzer::new(text.to_string()))
+}
+
+fn performance_to_renderer(
+    text: &str,
+    performance: Performance,
+    config: &GenerationConfig,
+) -> Result<TextRenderer> {
+    let instrument = config.instrument(category(text))?;
+    let mut renderer = Renderer::new(config.sample_rate, performance.track, instrument)
+        .with_articulation(config.articulation());
+    if let Some(velocities) = performance.velocities {
+        renderer = renderer.with_velocities(velocities);
+    }
+    Ok(Effects::new(
+        config.sample_rate,
+        renderer,
+        config.effects.clone(),
+    ))
+}
+
+pub fn text_to_wav(text: &str, config: &GenerationConfig, bit_depth: BitDepth) -> Result<Vec<u8>> {
+    config.validate()?;
+    let mut cursor = Cursor::new(vec![]);
+    write_performance_wav(
+        text,
+        text_to_performance(text, config),
+        config,
+        bit_depth,
+        &mut cursor,
+    )?;
+    Ok(cursor.into_inner())
+}
+
+// 曲全体をメモリに載せず、描画しながら書き出す
+fn write_performance_wav<W: Write + Seek>(
+    text: &str,
+    performance: Performance,
+    config: &GenerationConfig,
+    bit_depth: BitDepth,
+    writer: W,
+) -> Result<()> {
+    let text_analyzer = TextAnalyzer::new(text.to_string());
+    let renderer = performance_to_renderer(text, performance, config)?;
+
+    let format = WavFormat::new(1, config.sample_rate, bit_depth);
+    let metadata = WavMetadata {
+        title: text_analyzer.title(),
+        comment: format!("{}\n{}", text, config.with_seed(text).to_json()),
+        cues: sentence_cues(&text_analyzer, renderer.len()),
+    };
+    write_wav_with_metadata(&format, renderer, &metadata, writer)
+}
+
+pub fn text_to_flac(text: &str, config: &GenerationConfig, bit_depth: BitDepth) -> Result<Vec<u8>> {
+    config.validate()?;
+    performance_to_flac(text, text_to_performance(text, config), config, bit_depth)
+}
+
+fn performance_to_flac(
+    text: &str,
+    performance: Performance,
+    config: &GenerationConfig,
+    bit_depth: BitDepth,
+) -> Result<Vec<u8>> {
+    let format = WavFormat::new(1, config.sample_rate, bit_depth);
+    let samples: Vec<f64> = performance_to_renderer(text, performance, config)?.collect();
+    encode_flac(&format, &samples)
+}
+
+pub fn text_to_midi(text: &str, config: &GenerationConfig) -> Result<Vec<u8>> {
+    config.validate()?;
+    Ok(performance_to_midi(
+        text,
+        &text_to_performance(text, config),
+        config,
+    ))
+}
+
+// テキストの分野に合う音色を General MIDI のプログラムで指定する
+fn performance_to_midi(
+    text: &str,
+    performance: &Performance,
+    config: &GenerationConfig,
+) -> Vec<u8> {
+    let midi_track = MidiTrack {
+        track: &performance.track,
+        program: Some(category(text).program()),
+        velocities: performance.velocities.as_deref(),
+    };
+    encode_midi(&[midi_track], config.tempo)
+}
+
+// MIDI ではビット深度を使わない
+pub fn text_to_bytes(
+    text: &str,
+    config: &GenerationConfig,
+    format: OutputFormat,
+    bit_depth: BitDepth,
+) -> Result<Vec<u8>> {
+    match format {
+        OutputFormat::Wav => text_to_wav(text, config, bit_depth),
+        OutputFormat::Midi => text_to_midi(text, config),
+        OutputFormat::Flac => text_to_flac(text, config, bit_depth),
+    }
+}
+
+// WAV は描画しながらファイルに書き出し、それ以外はメモリ上で符号化してから書き出す。
+// 曲は一度だけ生成し、その長さ（フレーム数）を返す
+pub fn export_text(
+    text: &str,
+    config: &GenerationConfig,
+    format: OutputFormat,
+    bit_depth: BitDepth,
+    path: &Path,
+) -> Result<usize> {
+    config.validate()?;
+    let performance = text_to_performance(text, config);
+    let frames = total_frames(config.sample_rate, &performance.track);
+    match format {
+        OutputFormat::Wav => write_performance_wav(
+            text,
+            performance,
+            config,
+            bit_depth,
+            BufWriter::new(File::create(path)?),
+        )?,
+        OutputFormat::Midi => {
+            std::fs::write(path, performance_to_midi(text, &performance, config))?
+        }
+        OutputFormat::Flac => std::fs::write(
+            path,
+            performance_to_flac(text, performance, config, bit_depth)?,
+        )?,
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            effect::Effect,
+            humanize::{seed_from_text, Humanize},
+            pitch::Mode,
+            text2track::TrackType,
+        },
+        error::Error,
+        infrastructure::import_sf2::tests::build_sound_font,
+    };
+    use config::InstrumentKind;
+    use sha2::Digest;
+
+    fn config(sample_rate: u32) -> GenerationConfig {
+        GenerationConfig {
+            sample_rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_text_to_samples() {
+        // 16小節 × 1秒
+        let samples = text_to_samples("こんにちは", &config(8000)).unwrap();
+
+        assert_eq!(samples.len(), 16 * 8000);
+        assert!(samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+    }
+
+    #[test]
+    fn test_text_to_samples_with_tempo() {
+        // テンポを半分にすると倍の長さになる
+        let config = GenerationConfig {
+            tempo: 120.0,
+            ..config(8000)
+        };
+        let samples = text_to_samples("こんにちは", &config).unwrap();
+
+        assert_eq!(samples.len(), 32 * 8000);
+    }
+
+    #[test]
+    fn test_text_to_samples_hash() {
+        let samples_hash = |samples: Vec<f64>| {
+            let bytes: Vec<u8> = samples
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect();
+            format!("{:x}", sha2::Sha256::digest(bytes))
+        };
+        let performed = GenerationConfig {
+            key: Some(9),
+            scale: Some(Mode::Minor),
+            instrument: InstrumentKind::LegatoSine,
+            effects: vec![Effect::Gain { db: -3.0 }, Effect::FadeOut { seconds: 1.0 }],
+            humanize: Some(Humanize::default()),
+            ..config(8000)
+        };
+        let cases = vec![
+            (
+                ("こんにちは、私の名前はおもちです。", config(8000)),
+                "d6fc350428415c9511e18dd8f452585252902a3e6661be3db25fcb4264123cec",
+            ),
+            (
+                ("ヘイ！元気デスカ？", config(8000)),
+                "6032a6ee127e46e4f140e1381105ba7599b7e2b673b0022dc2f55552ee125f08",
+            ),
+            (
+                ("東京特許許可局に行く", config(8000)),
+                "2487b8c2150b051a14253c86d4ac7bfc8700e06108a524efebc09eacf90975f4",
+            ),
+            (
+                ("Why Japanese people!?", config(8000)),
+                "c6b0f916f645fe055aff92b498808dd4340b336e080348d74fefb683e156d93e",
+            ),
+            (
+                ("Why Japanese people!?", performed),
+                "55ad22e819eebd8500f5e65f8cffa3d3b1b7906e7c46f7a64b77cbb38bb75b8b",
+            ),
+        ];
+
+        for ((text, config), expected) in cases {
+            assert_eq!(
+                samples_hash(text_to_samples(text, &config).unwrap()),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_text_to_wav() {
+        let bytes = text_to_wav("Why Japanese people!?", &config(8000), BitDepth::Int24).unwrap();
+
+        let reader = hound::WavReader::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8000);
+        assert_eq!(reader.spec().bits_per_sample, 24);
+        assert_eq!(reader.duration(), 16 * 8000);
+    }
+
+    #[test]
+    fn test_export_text() {
+        let path = std::env::temp_dir().join("data2sound_test_export_text.wav");
+        let calls = || TEXT_TO_SAMPLES_CALLS.with(|calls| calls.get());
+
+        // WAV は曲全体のサンプルを集めずに書き出す
+        let before = calls();
+        let frames = export_text(
+            "こんにちは",
+            &config(8000),
+            OutputFormat::Wav,
+            BitDepth::Int16,
+            &path,
+        )
+        .unwrap();
+        assert_eq!(calls(), before);
+        assert_eq!(frames, 16 * 8000);
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            text_to_wav("こんにちは", &config(8000), BitDepth::Int16).unwrap()
+        );
+
+        // 書き出した曲の長さを返す
+        let frames = export_text(
+            "こんにちは",
+            &config(8000),
+            OutputFormat::Midi,
+            BitDepth::Int16,
+            &path,
+        )
+        .unwrap();
+        assert_eq!(frames, 16 * 8000);
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            text_to_midi("こんにちは", &config(8000)).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+
+        text_to_samples("こんにちは", &config(8000)).unwrap();
+        assert_eq!(calls(), before + 1);
+    }
+
+    #[test]
+    fn test_text_to_flac() {
+        let bytes = text_to_flac("こんにちは", &config(8000), BitDepth::Int16).unwrap();
+
+        let reader = claxon::FlacReader::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.streaminfo().sample_rate, 8000);
+        assert_eq!(reader.streaminfo().samples, Some(16 * 8000));
+    }
+
+    #[test]
+    fn test_text_to_midi() {
+        let bytes = text_to_midi("こんにちは", &config(8000)).unwrap();
+
+        assert_eq!(&bytes[..4], b"MThd");
+    }
+
+    #[test]
+    fn test_text_to_midi_program() {
+        // テキストの分野に合う音色をプログラムチェンジで指定する
+        let cases = vec![
+            ("こんにちは", 0),
+            ("江戸時代の歴史を学ぶ", 48),
+            ("サッカーの試合で優勝した", 30),
+        ];
+
+        for (text, program) in cases {
+            let bytes = text_to_midi(text, &config(8000)).unwrap();
+            assert!(
+                bytes.windows(2).any(|bytes| bytes == [0xC0, program]),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_text_to_samples_with_soundfont() {
+        let path = std::env::temp_dir().join("data2sound_test_soundfont.sf2");
+        std::fs::write(&path, build_sound_font()).unwrap();
+        let soundfont = GenerationConfig {
+            soundfont: Some(path.clone()),
+            ..config(8000)
+        };
+
+        // 歴史の文章には弦楽器のプリセットを使う
+        let text = "江戸時代の歴史を学ぶ";
+        let samples = text_to_samples(text, &soundfont).unwrap();
+        assert_eq!(samples.len(), 16 * 8000);
+        assert!(samples.iter().any(|sample| *sample != 0.0));
+        assert_ne!(samples, text_to_samples(text, &config(8000)).unwrap());
+
+        // スポーツのプリセットがないので生成できない
+        assert!(matches!(
+            text_to_samples("サッカーの試合で優勝した", &soundfont),
+            Err(Error::InvalidSoundFont(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_text_to_performance_with_style() {
+        let style = |style| GenerationConfig {
+            style: Some(style),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            text_to_performance("こんにちは", &style(TrackType::Katakana)),
+            text_to_performance("カタカナ", &GenerationConfig::default())
+        );
+    }
+
+    #[test]
+    fn test_text_to_samples_with_humanize() {
+        let humanize = |seed| GenerationConfig {
+            seed,
+            humanize: Some(Humanize::default()),
+            ..config(8000)
+        };
+
+        // 同じテキストなら毎回同じ音になり、シードを変えると別の演奏になる
+        let samples = text_to_samples("こんにちは", &humanize(None)).unwrap();
+        assert_eq!(
+            samples,
+            text_to_samples("こんにちは", &humanize(None)).unwrap()
+        );
+        assert_eq!(
+            samples,
+            text_to_samples("こんにちは", &humanize(Some(seed_from_text("こんにちは")))).unwrap()
+        );
+        assert_ne!(
+            samples,
+            text_to_samples("こんにちは", &humanize(Some(1))).unwrap()
+        );
+        assert_ne!(
+            samples,
+            text_to_samples("こんにちは", &config(8000)).unwrap()
+        );
+        assert_eq!(samples.len(), 16 * 8000);
+    }
+}
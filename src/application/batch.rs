@@ -0,0 +1,333 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::{Error, Result},
+    infrastructure::export_wav::BitDepth,
+};
+
+use super::{config::GenerationConfig, export_text, OutputFormat};
+
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchInput {
+    pub id: String,
+    pub text: String,
+    // レコードごとに設定を変えたい場合だけ指定する
+    pub config: Option<GenerationConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record {
+    id: Option<String>,
+    text: String,
+    config: Option<GenerationConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub input_sha256: String,
+    pub config: GenerationConfig,
+    pub format: OutputFormat,
+    pub bits_per_sample: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// ディレクトリなら中の *.txt を、ファイルなら JSONL として読む
+pub fn read_inputs(path: &Path) -> Result<Vec<BatchInput>> {
+    let inputs = if path.is_dir() {
+        read_directory(path)?
+    } else {
+        parse_jsonl(&std::fs::read_to_string(path)?)?
+    };
+
+    let mut ids = HashSet::new();
+    for input in &inputs {
+        if !ids.insert(file_name(&input.id)) {
+            return Err(Error::DuplicateId(input.id.clone()));
+        }
+    }
+    Ok(inputs)
+}
+
+fn read_directory(dir: &Path) -> Result<Vec<BatchInput>> {
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|extension| extension == "txt") {
+            paths.push(path);
+        }
+    }
+    // 出力やマニフェストの順番を実行環境によらず揃える
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            Ok(BatchInput {
+                id: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                text: std::fs::read_to_string(&path)?,
+                config: None,
+            })
+        })
+        .collect()
+}
+
+// id を省略したレコードには行番号を振る
+pub fn parse_jsonl(jsonl: &str) -> Result<Vec<BatchInput>> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let record: Record = serde_json::from_str(line)?;
+            if let Some(config) = &record.config {
+                config.validate()?;
+            }
+            Ok(BatchInput {
+                id: record.id.unwrap_or_else(|| format!("{:05}", i + 1)),
+                text: record.text,
+                config: record.config,
+            })
+        })
+        .collect()
+}
+
+// 入力ごとに並列で変換し、失敗したものもマニフェストに記録する
+pub fn run_batch(
+    inputs: &[BatchInput],
+    config: &GenerationConfig,
+    format: OutputFormat,
+    bit_depth: BitDepth,
+    output_dir: &Path,
+) -> Result<Vec<ManifestEntry>> {
+    std::fs::create_dir_all(output_dir)?;
+    let entries: Vec<ManifestEntry> = inputs
+        .par_iter()
+        .map(|input| convert(input, config, format, bit_depth, output_dir))
+        .collect();
+
+    std::fs::write(
+        output_dir.join(MANIFEST_FILE_NAME),
+        serde_json::to_string_pretty(&entries)?,
+    )?;
+    Ok(entries)
+}
+
+fn convert(
+    input: &BatchInput,
+    config: &GenerationConfig,
+    format: OutputFormat,
+    bit_depth: BitDepth,
+    output_dir: &Path,
+) -> ManifestEntry {
+    let config = input
+        .config
+        .as_ref()
+        .unwrap_or(config)
+        .with_seed(&input.text);
+    let output = output_dir.join(format!("{}.{}", file_name(&input.id), format.extension()));
+    let result = export_text(&input.text, &config, format, bit_depth, &output);
+
+    let mut entry = ManifestEntry {
+        id: input.id.clone(),
+        input_sha256: sha256(&input.text),
+        config,
+        format,
+        bits_per_sample: bit_depth.bits_per_sample(),
+        output: None,
+        duration_seconds: None,
+        error: None,
+    };
+    match result {
+        Ok(frames) => {
+            entry.duration_seconds = Some(frames as f64 / entry.config.sample_rate as f64);
+            entry.output = Some(output);
+        }
+        Err(error) => entry.error = Some(error.to_string()),
+    }
+    entry
+}
+
+fn sha256(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+// id をそのままファイル名に使うと出力先の外に書き出せてしまうので区切り文字を潰す
+fn file_name(id: &str) -> String {
+    let name: String = id
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match name.as_str() {
+        "" | "." | ".." => format!("_{}", name),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_jsonl() {
+        let jsonl = r#"{"id":"greeting","text":"こんにちは"}
+
+{"text":"カタカナ","config":{"tempo":120}}
+"#;
+
+        assert_eq!(
+            parse_jsonl(jsonl).unwrap(),
+            vec![
+                BatchInput {
+                    id: "greeting".to_string(),
+                    text: "こんにちは".to_string(),
+                    config: None,
+                },
+                BatchInput {
+                    id: "00003".to_string(),
+                    text: "カタカナ".to_string(),
+                    config: Some(GenerationConfig {
+                        tempo: 120.0,
+                        ..Default::default()
+                    }),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_jsonl_invalid() {
+        let cases = vec![
+            r#"{"id":"a"}"#,
+            r#"{"text":"a","config":{"tempo":0}}"#,
+            r#"{"text":"a","title":"b"}"#,
+            "text",
+        ];
+
+        for jsonl in cases {
+            assert!(parse_jsonl(jsonl).is_err());
+        }
+    }
+
+    #[test]
+    fn test_file_name() {
+        let cases = vec![
+            ("greeting", "greeting"),
+            ("こんにちは", "こんにちは"),
+            ("../etc/passwd", ".._etc_passwd"),
+            ("a\\b:c", "a_b_c"),
+            ("..", "_.."),
+            ("", "_"),
+        ];
+
+        for (id, expected) in cases {
+            assert_eq!(file_name(id), expected);
+        }
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_run_batch() {
+        let dir = std::env::temp_dir().join(format!("data2sound-batch-{}", std::process::id()));
+        let input_dir = dir.join("input");
+        std::fs::create_dir_all(&input_dir).unwrap();
+        std::fs::write(input_dir.join("b.txt"), "カタカナ").unwrap();
+        std::fs::write(input_dir.join("a.txt"), "こんにちは").unwrap();
+        std::fs::write(input_dir.join("notes.md"), "ignored").unwrap();
+
+        let inputs = read_inputs(&input_dir).unwrap();
+        assert_eq!(
+            inputs
+                .iter()
+                .map(|input| input.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+
+        let config = GenerationConfig {
+            sample_rate: 8000,
+            ..Default::default()
+        };
+        let output_dir = dir.join("output");
+        let entries = run_batch(
+            &inputs,
+            &config,
+            OutputFormat::Flac,
+            BitDepth::Int16,
+            &output_dir,
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "a");
+        assert_eq!(entries[0].input_sha256, sha256("こんにちは"));
+        assert_eq!(entries[0].output, Some(output_dir.join("a.flac")));
+        assert_eq!(entries[0].duration_seconds, Some(16.0));
+        assert!(entries.iter().all(|entry| entry.error.is_none()));
+        assert!(output_dir.join("b.flac").is_file());
+
+        let manifest: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(output_dir.join(MANIFEST_FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(manifest[1]["id"], "b");
+        assert_eq!(manifest[1]["format"], "flac");
+        assert_eq!(manifest[1]["config"]["sample_rate"], 8000);
+
+        // FLAC は 32 ビットに対応しないので、入力ごとのエラーとして記録される
+        let entries = run_batch(
+            &inputs,
+            &config,
+            OutputFormat::Flac,
+            BitDepth::Int32,
+            &output_dir,
+        )
+        .unwrap();
+        assert!(entries
+            .iter()
+            .all(|entry| entry.error.is_some() && entry.output.is_none()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_inputs_duplicate_id() {
+        let path =
+            std::env::temp_dir().join(format!("data2sound-batch-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"id\":\"a/b\",\"text\":\"x\"}\n{\"id\":\"a_b\",\"text\":\"y\"}\n",
+        )
+        .unwrap();
+
+        let result = read_inputs(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(Error::DuplicateId(id)) if id == "a_b"));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{ops::RangeInclusive, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{
+        category::Category,
+        effect::Effect,
+        humanize::{seed_from_text, Humanize},
+        instrument::{Instrument, LegatoSine, Sine},
+        pitch::{Key, Mode},
+        pure_tone::ToneAndDuration,
+        renderer::Articulation,
+        text2track::{TrackType, DEFAULT_TEMPO},
+    },
+    error::{Error, Result},
+    infrastructure::import_sf2::import_sf2,
+};
+
+use super::DEFAULT_SAMPLE_RATE;
+
+// 極端なテンポでは音符が0フレームになったり、バッファが際限なく大きくなる
+const TEMPO_RANGE: RangeInclusive<f32> = 20.0..=1000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstrumentKind {
+    Sine,
+    LegatoSine,
+}
+
+// WASM と CLI で共通の生成パラメータ。JSON で省略した項目は既定値になる
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationConfig {
+    pub sample_rate: u32,
+    // 四分音符の BPM
+    pub tempo: f32,
+    // 主音のピッチクラス（C = 0）。省略するとテキストから決まる調のまま
+    pub key: Option<u8>,
+    pub scale: Option<Mode>,
+    pub instrument: InstrumentKind,
+    pub effects: Vec<Effect>,
+    // 省略するとテキストのハッシュをシードにする
+    pub seed: Option<u64>,
+    // 省略すると揺らぎを付けずに演奏する
+    pub humanize: Option<Humanize>,
+    // 省略するとテキストの文字種から曲調を決める
+    pub style: Option<TrackType>,
+    // SoundFont 2 ファイル。指定すると instrument の代わりに、テキストの分野に合う音色で演奏する
+    pub soundfont: Option<PathBuf>,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            tempo: DEFAULT_TEMPO,
+            key: None,
+            scale: None,
+            instrument: InstrumentKind::Sine,
+            effects: vec![],
+            seed: None,
+            humanize: None,
+            style: None,
+            soundfont: None,
+        }
+    }
+}
+
+impl GenerationConfig {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let config: Self = serde_json::from_str(json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.sample_rate == 0 {
+            return Err(Error::InvalidConfig("sample_rate must be positive"));
+        }
+        if !TEMPO_RANGE.contains(&self.tempo) {
+            return Err(Error::InvalidConfig("tempo must be from 20 to 1000 BPM"));
+        }
+        if self.key.is_some_and(|key| key >= 12) {
+            return Err(Error::InvalidConfig(
+                "key must be a pitch class from 0 to 11",
+            ));
+        }
+        if self.humanize.is_some_and(|humanize| !humanize.is_valid()) {
+            return Err(Error::InvalidConfig(
+                "humanize amounts must be non-negative and at most 1",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn seed(&self, text: &str) -> u64 {
+        self.seed.unwrap_or_else(|| seed_from_text(text))
+    }
+
+    // 出力に記録して同じ音を再現できるよう、実際に使うシードを埋める
+    pub fn with_seed(&self, text: &str) -> Self {
+        Self {
+            seed: Some(self.seed(text)),
+            ..self.clone()
+        }
+    }
+
+    pub fn key(&self, original: Key) -> Key {
+        Key::new(
+            self.key.unwrap_or(original.tonic),
+            self.scale.unwrap_or(original.mode),
+        )
+    }
+
+    // テキストから作った曲を指定の調とテンポに合わせる
+    pub fn arrange(&self, track: &[ToneAndDuration], original: Key) -> Vec<ToneAndDuration> {
+        let key = self.key(original);
+        let stretch = DEFAULT_TEMPO / self.tempo;
+        track
+            .iter()
+            .map(|tone| ToneAndDuration {
+                frequency: original.transpose(&key, tone.frequency),
+                duration: tone.duration * stretch,
+            })
+            .collect()
+    }
+
+    pub fn instrument(&self, category: Category) -> Result<Box<dyn Instrument + Send>> {
+        if let Some(path) = &self.soundfont {
+            let sampler =
+                import_sf2(path)?
+                    .sampler_for_category(category)
+                    .ok_or(Error::InvalidSoundFont(
+                        "no preset for the detected category",
+                    ))?;
+            return Ok(Box::new(sampler));
+        }
+        Ok(match self.instrument {
+            InstrumentKind::Sine => Box::new(Sine),
+            InstrumentKind::LegatoSine => Box::new(LegatoSine::new()),
+        })
+    }
+
+    pub fn articulation(&self) -> Articulation {
+        // サンプラーは音符ごとにサンプルを頭から鳴らす
+        if self.soundfont.is_some() {
+            return Articulation::Detached;
+        }
+        match self.instrument {
+            InstrumentKind::Sine => Articulation::Detached,
+            InstrumentKind::LegatoSine => Articulation::Legato,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let cases = vec![
+            ("{}", GenerationConfig::default()),
+            (
+                r#"{"soundfont":"strings.sf2"}"#,
+                GenerationConfig {
+                    soundfont: Some(PathBuf::from("strings.sf2")),
+                    ..Default::default()
+                },
+            ),
+            (
+                r#"{"sample_rate":8000,"tempo":120,"key":2,"scale":"minor","instrument":"legato_sine","effects":[{"type":"gain","db":-6.0}],"seed":42,"humanize":{"timing":0.02},"style":"kanji"}"#,
+                GenerationConfig {
+                    sample_rate: 8000,
+                    tempo: 120.0,
+                    key: Some(2),
+                    scale: Some(Mode::Minor),
+                    instrument: InstrumentKind::LegatoSine,
+                    effects: vec![Effect::Gain { db: -6.0 }],
+                    seed: Some(42),
+                    humanize: Some(Humanize {
+                        timing: 0.02,
+                        ..Default::default()
+                    }),
+                    style: Some(TrackType::Kanji),
+                    soundfont: None,
+                },
+            ),
+        ];
+
+        for (json, expected) in cases {
+            let config = GenerationConfig::from_json(json).unwrap();
+            assert_eq!(config, expected);
+            assert_eq!(
+                GenerationConfig::from_json(&config.to_json()).unwrap(),
+                config
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_json_invalid() {
+        let cases = vec![
+            r#"{"sample_rate":0}"#,
+            r#"{"tempo":-1}"#,
+            r#"{"tempo":1e300}"#,
+            r#"{"tempo":1e-30}"#,
+            r#"{"key":12}"#,
+            r#"{"humanize":{"ornament":1.5}}"#,
+            r#"{"scale":"dorian"}"#,
+            r#"{"volume":1}"#,
+            "not json",
+        ];
+
+        for json in cases {
+            assert!(GenerationConfig::from_json(json).is_err());
+        }
+    }
+
+    #[test]
+    fn test_arrange() {
+        let track = vec![
+            ToneAndDuration {
+                frequency: 261.63,
+                duration: 0.5,
+            },
+            ToneAndDuration {
+                frequency: 0.0,
+                duration: 0.25,
+            },
+        ];
+        let config = GenerationConfig {
+            tempo: 120.0,
+            key: Some(2),
+            ..Default::default()
+        };
+
+        let arranged = config.arrange(&track, Key::new(0, Mode::Major));
+        assert!((arranged[0].frequency - 293.66).abs() < 0.01);
+        assert_eq!(arranged[0].duration, 1.0);
+        assert_eq!(arranged[1].frequency, 0.0);
+        assert_eq!(arranged[1].duration, 0.5);
+    }
+}
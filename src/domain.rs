@@ -1,3 +1,14 @@
+pub mod category;
+pub mod cue;
+pub mod effect;
+pub mod form;
+pub mod humanize;
+pub mod instrument;
+pub mod notation;
+pub mod pitch;
+pub mod portable_math;
 pub mod pure_tone;
+pub mod renderer;
+pub mod sampler;
 pub mod text2track;
 pub mod text_analyzer;
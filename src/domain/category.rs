@@ -0,0 +1,99 @@
+use super::text_analyzer::TextAnalyzer;
+
+const HISTORY_KEYWORDS: [&str; 14] = [
+    "歴史", "時代", "戦国", "江戸", "幕府", "天皇", "王朝", "戦争", "history", "ancient", "empire",
+    "century", "dynasty", "battle",
+];
+const SPORTS_KEYWORDS: [&str; 14] = [
+    "スポーツ",
+    "試合",
+    "選手",
+    "優勝",
+    "サッカー",
+    "野球",
+    "五輪",
+    "オリンピック",
+    "sports",
+    "match",
+    "team",
+    "player",
+    "olympic",
+    "goal",
+];
+const SCIENCE_KEYWORDS: [&str; 14] = [
+    "科学",
+    "研究",
+    "実験",
+    "宇宙",
+    "物理",
+    "化学",
+    "技術",
+    "論文",
+    "science",
+    "research",
+    "experiment",
+    "physics",
+    "technology",
+    "data",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    History,
+    Sports,
+    Science,
+    General,
+}
+
+impl Category {
+    pub fn detect(text_analyzer: &TextAnalyzer) -> Self {
+        let binding = [
+            (
+                text_analyzer.count_keywords(&HISTORY_KEYWORDS),
+                Category::History,
+            ),
+            (
+                text_analyzer.count_keywords(&SPORTS_KEYWORDS),
+                Category::Sports,
+            ),
+            (
+                text_analyzer.count_keywords(&SCIENCE_KEYWORDS),
+                Category::Science,
+            ),
+        ];
+
+        binding
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .max_by_key(|(count, _)| *count)
+            .map(|(_, category)| *category)
+            .unwrap_or(Category::General)
+    }
+
+    // General MIDI のプログラム番号（0始まり）
+    pub fn program(&self) -> u8 {
+        match self {
+            Category::History => 48, // String Ensemble 1（オーケストラ）
+            Category::Sports => 30,  // Distortion Guitar（ロック）
+            Category::Science => 81, // Lead 2 (sawtooth)（電子音楽）
+            Category::General => 0,  // Acoustic Grand Piano
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::history("江戸時代の歴史を学ぶ", Category::History)]
+    #[case::sports("サッカーの試合で選手が優勝した", Category::Sports)]
+    #[case::science("宇宙物理学の研究論文", Category::Science)]
+    #[case::english("The history of the Roman Empire", Category::History)]
+    #[case::general("こんにちは、私の名前はおもちです。", Category::General)]
+    fn test_detect(#[case] input: String, #[case] expected: Category) {
+        let text_analyzer = TextAnalyzer::new(input);
+        assert_eq!(Category::detect(&text_analyzer), expected);
+    }
+}
@@ -0,0 +1,51 @@
+use super::text_analyzer::TextAnalyzer;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub frame: u32,
+    pub label: String,
+}
+
+// 曲はテキストの位置と直接対応していないため、文の開始位置を曲の長さに比例させて配置する
+pub fn sentence_cues(text_analyzer: &TextAnalyzer, total_frames: usize) -> Vec<Cue> {
+    let length = text_analyzer.length();
+    text_analyzer
+        .sentences()
+        .into_iter()
+        .map(|sentence| Cue {
+            frame: (sentence.start as f64 / length as f64 * total_frames as f64) as u32,
+            label: sentence.text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentence_cues() {
+        let text_analyzer = TextAnalyzer::new("吾輩は猫である。名前はまだ無い。".to_string());
+
+        assert_eq!(
+            sentence_cues(&text_analyzer, 16000),
+            vec![
+                Cue {
+                    frame: 0,
+                    label: "吾輩は猫である。".to_string(),
+                },
+                Cue {
+                    frame: 8000,
+                    label: "名前はまだ無い。".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sentence_cues_empty_text() {
+        let text_analyzer = TextAnalyzer::new("".to_string());
+
+        assert_eq!(sentence_cues(&text_analyzer, 16000), vec![]);
+    }
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use super::portable_math::db_to_amplitude;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Effect {
+    // デシベル単位で音量を変える
+    Gain { db: f64 },
+    FadeIn { seconds: f64 },
+    FadeOut { seconds: f64 },
+}
+
+impl Effect {
+    fn apply(&self, sample_rate: u32, position: usize, total: usize, sample: f64) -> f64 {
+        match *self {
+            Effect::Gain { db } => sample * db_to_amplitude(db),
+            Effect::FadeIn { seconds } => sample * fade(seconds * sample_rate as f64, position),
+            Effect::FadeOut { seconds } => {
+                sample * fade(seconds * sample_rate as f64, total - position - 1)
+            }
+        }
+    }
+}
+
+// 端から frames 以内なら距離に比例して小さくする
+fn fade(frames: f64, distance: usize) -> f64 {
+    if frames <= 0.0 {
+        1.0
+    } else {
+        (distance as f64 / frames).min(1.0)
+    }
+}
+
+// 曲の長さが分かっていれば、全体を保持しなくてもエフェクトをかけられる
+pub struct Effects<I> {
+    samples: I,
+    effects: Vec<Effect>,
+    sample_rate: u32,
+    position: usize,
+    total: usize,
+}
+
+impl<I: ExactSizeIterator<Item = f64>> Effects<I> {
+    pub fn new(sample_rate: u32, samples: I, effects: Vec<Effect>) -> Self {
+        Self {
+            total: samples.len(),
+            samples,
+            effects,
+            sample_rate,
+            position: 0,
+        }
+    }
+}
+
+impl<I: ExactSizeIterator<Item = f64>> Iterator for Effects<I> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let sample = self.samples.next()?;
+        let sample = self.effects.iter().fold(sample, |sample, effect| {
+            effect.apply(self.sample_rate, self.position, self.total, sample)
+        });
+        self.position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+impl<I: ExactSizeIterator<Item = f64>> ExactSizeIterator for Effects<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effects() {
+        let cases = vec![
+            (vec![], vec![1.0; 5]),
+            (vec![Effect::Gain { db: -20.0 }], vec![0.1; 5]),
+            (
+                vec![Effect::FadeIn { seconds: 2.0 }],
+                vec![0.0, 0.5, 1.0, 1.0, 1.0],
+            ),
+            (
+                vec![Effect::FadeOut { seconds: 2.0 }],
+                vec![1.0, 1.0, 1.0, 0.5, 0.0],
+            ),
+            (
+                vec![
+                    Effect::FadeIn { seconds: 4.0 },
+                    Effect::FadeOut { seconds: 4.0 },
+                ],
+                vec![0.0, 0.1875, 0.25, 0.1875, 0.0],
+            ),
+        ];
+
+        for (effects, expected) in cases {
+            let samples: Vec<f64> = Effects::new(1, vec![1.0; 5].into_iter(), effects).collect();
+            assert_eq!(samples.len(), expected.len());
+            for (sample, expected) in samples.iter().zip(expected) {
+                assert!((sample - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_effect_json() {
+        let json = r#"[{"type":"gain","db":-6.0},{"type":"fade_out","seconds":0.5}]"#;
+        let effects: Vec<Effect> = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            effects,
+            vec![Effect::Gain { db: -6.0 }, Effect::FadeOut { seconds: 0.5 }]
+        );
+        assert_eq!(serde_json::to_string(&effects).unwrap(), json);
+    }
+}
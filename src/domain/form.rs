@@ -0,0 +1,147 @@
+use std::ops::Range;
+
+use super::pure_tone::ToneAndDuration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParagraphRole {
+    Opening,
+    Body,
+    Conclusion,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    Intro,
+    // 本文
+    Verse,
+    // 要約や結論
+    Chorus,
+    // 転換部
+    Bridge,
+    Outro,
+}
+
+impl Section {
+    // 16小節の旋律のうち、このセクションで使う小節
+    fn bars(&self) -> Range<usize> {
+        match self {
+            Section::Intro => 0..4,
+            Section::Verse => 4..8,
+            Section::Bridge => 8..12,
+            Section::Chorus => 12..16,
+            // コーラスの終わりを繰り返して締める
+            Section::Outro => 14..16,
+        }
+    }
+}
+
+// 最初の段落を導入、最後の段落を結論とみなす
+pub fn paragraph_roles(paragraphs: usize) -> Vec<ParagraphRole> {
+    (0..paragraphs)
+        .map(|i| match i {
+            0 => ParagraphRole::Opening,
+            i if i + 1 == paragraphs => ParagraphRole::Conclusion,
+            _ => ParagraphRole::Body,
+        })
+        .collect()
+}
+
+// 冒頭でコーラスを一度聴かせ、結論で繰り返す。最後の本文の段落は
+// ブリッジにして、結論のコーラスの前に対比を作る
+pub fn plan_form(roles: &[ParagraphRole]) -> Vec<Section> {
+    let last_body = roles.iter().rposition(|role| *role == ParagraphRole::Body);
+    let mut form = vec![Section::Intro];
+    for (i, role) in roles.iter().enumerate() {
+        match role {
+            ParagraphRole::Opening => form.extend([Section::Verse, Section::Chorus]),
+            ParagraphRole::Body if Some(i) == last_body => form.push(Section::Bridge),
+            ParagraphRole::Body => form.push(Section::Verse),
+            ParagraphRole::Conclusion => form.push(Section::Chorus),
+        }
+    }
+    form.push(Section::Outro);
+    form
+}
+
+// 各小節を1秒として、セクションの小節に含まれる音符を並べる
+pub fn arrange_form(melody: &[ToneAndDuration], form: &[Section]) -> Vec<ToneAndDuration> {
+    form.iter()
+        .flat_map(|section| bars(melody, section.bars()))
+        .collect()
+}
+
+// どの旋律も小節線をまたぐ音符はないので、音符の開始位置で小節を決める
+fn bars(melody: &[ToneAndDuration], bars: Range<usize>) -> Vec<ToneAndDuration> {
+    let mut elapsed = 0.0_f64;
+    melody
+        .iter()
+        .filter(|tone| {
+            // 三連符などの累積誤差で小節の頭が前の小節に入らないようにする
+            let bar = (elapsed + 1e-3).floor() as usize;
+            elapsed += tone.duration as f64;
+            bars.contains(&bar)
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+        ToneAndDuration {
+            frequency,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_paragraph_roles() {
+        use ParagraphRole::*;
+        let cases = vec![
+            (0, vec![]),
+            (1, vec![Opening]),
+            (2, vec![Opening, Conclusion]),
+            (4, vec![Opening, Body, Body, Conclusion]),
+        ];
+
+        for (paragraphs, expected) in cases {
+            assert_eq!(paragraph_roles(paragraphs), expected);
+        }
+    }
+
+    #[test]
+    fn test_plan_form() {
+        use Section::*;
+        let cases = vec![
+            (2, vec![Intro, Verse, Chorus, Chorus, Outro]),
+            (3, vec![Intro, Verse, Chorus, Bridge, Chorus, Outro]),
+            (4, vec![Intro, Verse, Chorus, Verse, Bridge, Chorus, Outro]),
+        ];
+
+        for (paragraphs, expected) in cases {
+            assert_eq!(plan_form(&paragraph_roles(paragraphs)), expected);
+        }
+    }
+
+    #[test]
+    fn test_arrange_form() {
+        // 小節ごとに音高を変えた16小節。3小節目は三連符
+        let melody: Vec<ToneAndDuration> = (0..16)
+            .flat_map(|bar| match bar {
+                2 => vec![tone(bar as f32, 1.0 / 3.0); 3],
+                _ => vec![tone(bar as f32, 1.0 / 2.0); 2],
+            })
+            .collect();
+
+        let track = arrange_form(&melody, &[Section::Intro, Section::Outro]);
+        let frequencies: Vec<f32> = track.iter().map(|tone| tone.frequency).collect();
+        assert_eq!(
+            frequencies,
+            vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 2.0, 3.0, 3.0, 14.0, 14.0, 15.0, 15.0]
+        );
+        let seconds: f32 = track.iter().map(|tone| tone.duration).sum();
+        assert!((seconds - 6.0).abs() < 1e-5);
+    }
+}
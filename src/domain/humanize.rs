@@ -0,0 +1,290 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    pitch::{frequency_to_midi, shift_semitones, Key, Mode},
+    pure_tone::ToneAndDuration,
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+const BASE_VELOCITY: f32 = 0.8;
+// 装飾音1つの長さ（秒）
+const ORNAMENT_SECONDS: f32 = 1.0 / 32.0;
+
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MINOR_SCALE: [i32; 7] = [0, 2, 3, 5, 7, 8, 10];
+
+// 演奏のゆらぎの大きさ。すべて 0 にすると元の曲と同じ長さ・音程のまま強さだけが揃う
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Humanize {
+    // 音符の境界をずらす最大幅（秒）
+    pub timing: f32,
+    // 強さのばらつき（0〜1）
+    pub velocity: f32,
+    // 音階上の隣の音に置き換える確率
+    pub variation: f32,
+    // 装飾音を付ける確率
+    pub ornament: f32,
+}
+
+impl Default for Humanize {
+    fn default() -> Self {
+        Self {
+            timing: 0.01,
+            velocity: 0.15,
+            variation: 0.1,
+            ornament: 0.1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Performance {
+    pub track: Vec<ToneAndDuration>,
+    pub velocities: Option<Vec<f32>>,
+}
+
+// 標準の Hasher は実行環境やバージョンで値が変わりうるので FNV-1a で固定する
+pub fn seed_from_text(text: &str) -> u64 {
+    text.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+impl Humanize {
+    pub fn is_valid(&self) -> bool {
+        let probability = 0.0..=1.0;
+        self.timing >= 0.0
+            && probability.contains(&self.velocity)
+            && probability.contains(&self.variation)
+            && probability.contains(&self.ornament)
+    }
+
+    // 同じシードなら常に同じ演奏になる。乱数は決まった順番で引く
+    pub fn perform(&self, track: &[ToneAndDuration], key: &Key, seed: u64) -> Performance {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let track = self.vary(track, key, &mut rng);
+        let track = self.ornament(&track, key, &mut rng);
+        let track = self.shift_timing(&track, &mut rng);
+        let velocities = track
+            .iter()
+            .map(|_| (BASE_VELOCITY + rng.gen_range(-1.0..=1.0) * self.velocity).clamp(0.0, 1.0))
+            .collect();
+        Performance {
+            track,
+            velocities: Some(velocities),
+        }
+    }
+
+    // 曲の始まりと終わりの音は変えない
+    fn vary(
+        &self,
+        track: &[ToneAndDuration],
+        key: &Key,
+        rng: &mut ChaCha8Rng,
+    ) -> Vec<ToneAndDuration> {
+        let last = track.len().saturating_sub(1);
+        track
+            .iter()
+            .enumerate()
+            .map(|(i, tone)| {
+                let varied = rng.gen_bool(self.variation as f64);
+                let step = if rng.gen_bool(0.5) { 1 } else { -1 };
+                if varied && i != 0 && i != last && tone.frequency > 0.0 {
+                    ToneAndDuration {
+                        frequency: scale_neighbor(key, tone.frequency, step),
+                        ..tone.clone()
+                    }
+                } else {
+                    tone.clone()
+                }
+            })
+            .collect()
+    }
+
+    // 長さに余裕のある音に、モルデントか下からの前打音を付ける
+    fn ornament(
+        &self,
+        track: &[ToneAndDuration],
+        key: &Key,
+        rng: &mut ChaCha8Rng,
+    ) -> Vec<ToneAndDuration> {
+        let mut ornamented = vec![];
+        for note in track {
+            let ornamented_note = rng.gen_bool(self.ornament as f64);
+            let mordent = rng.gen_bool(0.5);
+            if !ornamented_note || note.frequency <= 0.0 || note.duration < ORNAMENT_SECONDS * 4.0 {
+                ornamented.push(note.clone());
+            } else if mordent {
+                let upper = scale_neighbor(key, note.frequency, 1);
+                ornamented.push(tone(note.frequency, ORNAMENT_SECONDS));
+                ornamented.push(tone(upper, ORNAMENT_SECONDS));
+                ornamented.push(tone(note.frequency, note.duration - ORNAMENT_SECONDS * 2.0));
+            } else {
+                let lower = scale_neighbor(key, note.frequency, -1);
+                ornamented.push(tone(lower, ORNAMENT_SECONDS));
+                ornamented.push(tone(note.frequency, note.duration - ORNAMENT_SECONDS));
+            }
+        }
+        ornamented
+    }
+
+    // 音符の境界を前後にずらす。曲の両端は動かさないので全体の長さは変わらない
+    fn shift_timing(
+        &self,
+        track: &[ToneAndDuration],
+        rng: &mut ChaCha8Rng,
+    ) -> Vec<ToneAndDuration> {
+        let offsets: Vec<f32> = track
+            .windows(2)
+            .map(|pair| {
+                let limit = (pair[0].duration.min(pair[1].duration) / 4.0).min(self.timing);
+                rng.gen_range(-1.0..=1.0) * limit
+            })
+            .collect();
+        track
+            .iter()
+            .enumerate()
+            .map(|(i, note)| {
+                let end = offsets.get(i).copied().unwrap_or(0.0);
+                let start = if i == 0 { 0.0 } else { offsets[i - 1] };
+                tone(note.frequency, note.duration + end - start)
+            })
+            .collect()
+    }
+}
+
+fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+    ToneAndDuration {
+        frequency,
+        duration,
+    }
+}
+
+// 調の音階に沿って step の方向へ1音動かす
+fn scale_neighbor(key: &Key, frequency: f32, step: i32) -> f32 {
+    let scale = match key.mode {
+        Mode::Major => MAJOR_SCALE,
+        Mode::Minor => MINOR_SCALE,
+    };
+    let note = frequency_to_midi(frequency).round() as i32;
+    let mut neighbor = note + step;
+    while !scale.contains(&(neighbor - key.tonic as i32).rem_euclid(12)) {
+        neighbor += step;
+    }
+    shift_semitones(frequency, neighbor - note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn melody() -> Vec<ToneAndDuration> {
+        [
+            261.63, 293.66, 0.0, 329.63, 349.23, 392.00, 440.00, 493.88, 523.25,
+        ]
+        .iter()
+        .map(|&frequency| tone(frequency, 0.25))
+        .collect()
+    }
+
+    fn total_seconds(track: &[ToneAndDuration]) -> f32 {
+        track.iter().map(|tone| tone.duration).sum()
+    }
+
+    #[test]
+    fn test_seed_from_text() {
+        let cases = vec![
+            ("", 0xcbf29ce484222325),
+            ("a", 0xaf63dc4c8601ec8c),
+            ("foobar", 0x85944171f73967e8),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(seed_from_text(text), expected);
+        }
+    }
+
+    #[test]
+    fn test_scale_neighbor() {
+        let c_major = Key::new(0, Mode::Major);
+        let a_minor = Key::new(9, Mode::Minor);
+        let cases = vec![
+            ((c_major, 329.63, 1), 349.23),
+            ((c_major, 349.23, -1), 329.63),
+            ((c_major, 493.88, 1), 523.25),
+            ((c_major, 261.63, -1), 246.94),
+            ((a_minor, 261.63, 1), 293.66),
+            ((a_minor, 392.00, 1), 440.00),
+            ((a_minor, 440.00, -1), 392.00),
+        ];
+
+        for ((key, frequency, step), expected) in cases {
+            assert!((scale_neighbor(&key, frequency, step) - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_perform_is_deterministic() {
+        let key = Key::new(0, Mode::Major);
+        let humanize = Humanize {
+            variation: 0.5,
+            ornament: 0.5,
+            ..Default::default()
+        };
+
+        let performance = humanize.perform(&melody(), &key, 1);
+        assert_eq!(performance, humanize.perform(&melody(), &key, 1));
+        assert_ne!(performance, humanize.perform(&melody(), &key, 2));
+
+        assert!((total_seconds(&performance.track) - total_seconds(&melody())).abs() < 1e-5);
+        assert!(performance.track.len() > melody().len());
+        assert!(performance.track.iter().all(|tone| tone.duration > 0.0));
+        assert_eq!(performance.track.first().unwrap().frequency, 261.63);
+        assert_eq!(performance.track.last().unwrap().frequency, 523.25);
+        let velocities = performance.velocities.unwrap();
+        assert_eq!(velocities.len(), performance.track.len());
+        assert!(velocities
+            .iter()
+            .all(|velocity| (0.65..=0.95).contains(velocity)));
+    }
+
+    #[test]
+    fn test_perform_without_variation() {
+        let humanize = Humanize {
+            timing: 0.0,
+            velocity: 0.0,
+            variation: 0.0,
+            ornament: 0.0,
+        };
+        let performance = humanize.perform(&melody(), &Key::new(0, Mode::Major), 1);
+
+        assert_eq!(performance.track, melody());
+        assert_eq!(
+            performance.velocities,
+            Some(vec![BASE_VELOCITY; melody().len()])
+        );
+    }
+
+    #[test]
+    fn test_shift_timing() {
+        let humanize = Humanize {
+            timing: 0.01,
+            ..Default::default()
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let track = humanize.shift_timing(&melody(), &mut rng);
+
+        let mut elapsed = 0.0;
+        for (original, shifted) in melody().iter().zip(&track) {
+            elapsed += shifted.duration;
+            // 前後の境界がそれぞれ最大 0.01 秒ずれる
+            assert_eq!(original.frequency, shifted.frequency);
+            assert!((shifted.duration - original.duration).abs() <= 0.02 + 1e-6);
+        }
+        assert!((elapsed - total_seconds(&melody())).abs() < 1e-5);
+    }
+}
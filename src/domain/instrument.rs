@@ -0,0 +1,150 @@
+use super::portable_math::{exp2, log2, sine};
+
+pub trait Instrument {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64>;
+}
+
+impl<I: Instrument + ?Sized> Instrument for &mut I {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64> {
+        (**self).render_note(sample_rate, frequency, frames)
+    }
+}
+
+impl<I: Instrument + ?Sized> Instrument for Box<I> {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64> {
+        (**self).render_note(sample_rate, frequency, frames)
+    }
+}
+
+pub struct Sine;
+
+impl Instrument for Sine {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64> {
+        let step = frequency as f64 / sample_rate as f64;
+        let mut phase = 0.0;
+        (0..frames)
+            .map(|_| {
+                let sample = sine(phase);
+                phase = (phase + step).fract();
+                sample
+            })
+            .collect()
+    }
+}
+
+// 音符の境界で位相を引き継ぐサイン波。レガートでつなげてもクリックノイズが出ない
+pub struct LegatoSine {
+    glide_seconds: f32,
+    phase: f64,
+    frequency: Option<f32>,
+}
+
+impl LegatoSine {
+    pub fn new() -> Self {
+        Self::with_glide(0.0)
+    }
+
+    // 前の音から glide_seconds かけて音程を滑らかに変える（ポルタメント）
+    pub fn with_glide(glide_seconds: f32) -> Self {
+        Self {
+            glide_seconds,
+            phase: 0.0,
+            frequency: None,
+        }
+    }
+}
+
+impl Default for LegatoSine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Instrument for LegatoSine {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64> {
+        // 休符のあとはグライドせずに次の音から始める
+        if frequency <= 0.0 {
+            self.frequency = None;
+            return vec![0.0; frames];
+        }
+
+        let from = self.frequency.unwrap_or(frequency);
+        let glide_frames = (self.glide_seconds * sample_rate as f32) as usize;
+        let interval = log2(frequency as f64 / from as f64);
+        let mut current = from;
+        let samples = (0..frames)
+            .map(|i| {
+                // 音程として等速に変わるよう、周波数は指数的に補間する
+                current = if i < glide_frames {
+                    (from as f64 * exp2(interval * i as f64 / glide_frames as f64)) as f32
+                } else {
+                    frequency
+                };
+                let sample = sine(self.phase);
+                self.phase = (self.phase + current as f64 / sample_rate as f64).fract();
+                sample
+            })
+            .collect();
+        // グライドの途中で音符が終わった場合は、そこから次の音へ向かう
+        self.frequency = Some(current);
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max_step(samples: &[f64]) -> f64 {
+        samples
+            .windows(2)
+            .map(|window| (window[1] - window[0]).abs())
+            .fold(0.0, f64::max)
+    }
+
+    fn zero_crossings(samples: &[f64]) -> usize {
+        samples
+            .windows(2)
+            .filter(|window| (window[0] < 0.0) != (window[1] < 0.0))
+            .count()
+    }
+
+    #[test]
+    fn test_legato_sine_phase_continuity() {
+        let mut instrument = LegatoSine::new();
+        let mut samples = instrument.render_note(8000, 330.0, 1001);
+        samples.extend(instrument.render_note(8000, 440.0, 1000));
+
+        // 1サンプルで進む位相の分しか変化しない
+        let limit = std::f64::consts::TAU * 440.0 / 8000.0;
+        assert!(max_step(&samples) <= limit);
+
+        // 毎回位相 0 から始める Sine は境界で跳ぶ
+        let mut samples = Sine.render_note(8000, 330.0, 1001);
+        samples.extend(Sine.render_note(8000, 440.0, 1000));
+        assert!(max_step(&samples) > limit);
+    }
+
+    #[test]
+    fn test_legato_sine_glide() {
+        let mut instrument = LegatoSine::with_glide(0.5);
+        instrument.render_note(8000, 100.0, 8000);
+        let glide = instrument.render_note(8000, 400.0, 8000);
+
+        // 100Hz から 400Hz への指数的なグライドの平均は約 216Hz なので、0.5 秒で約 108 周期
+        let crossings = zero_crossings(&glide[..4000]);
+        assert!((200..235).contains(&crossings));
+        assert_eq!(zero_crossings(&glide[4000..]), 400);
+    }
+
+    #[test]
+    fn test_legato_sine_rest() {
+        let mut instrument = LegatoSine::with_glide(0.5);
+        instrument.render_note(8000, 100.0, 8000);
+
+        assert_eq!(instrument.render_note(8000, 0.0, 10), vec![0.0; 10]);
+        // 休符のあとはグライドしない
+        let note = instrument.render_note(8000, 400.0, 4000);
+        assert!((399..=401).contains(&zero_crossings(&note)));
+    }
+}
@@ -0,0 +1,460 @@
+use super::{
+    pitch::{frequency_to_midi, Key, SpelledPitch},
+    pure_tone::ToneAndDuration,
+};
+
+// 四分音符あたりの分割数。三連符と16分音符の両方を整数で表せる
+pub const DIVISIONS_PER_QUARTER: u32 = 12;
+pub const BEATS_PER_MEASURE: u32 = 4;
+const DIVISIONS_PER_MEASURE: u32 = DIVISIONS_PER_QUARTER * BEATS_PER_MEASURE;
+// 最も短い音価（16分音符の三連符）の分割数
+const MIN_DIVISIONS: u32 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoteValue {
+    Whole,
+    Half,
+    Quarter,
+    Eighth,
+    Sixteenth,
+}
+
+// (分割数, 音価, 付点の数, 三連符か)
+const NOTE_VALUES: [(u32, NoteValue, u8, bool); 12] = [
+    (48, NoteValue::Whole, 0, false),
+    (36, NoteValue::Half, 1, false),
+    (24, NoteValue::Half, 0, false),
+    (18, NoteValue::Quarter, 1, false),
+    (16, NoteValue::Half, 0, true),
+    (12, NoteValue::Quarter, 0, false),
+    (9, NoteValue::Eighth, 1, false),
+    (8, NoteValue::Quarter, 0, true),
+    (6, NoteValue::Eighth, 0, false),
+    (4, NoteValue::Eighth, 0, true),
+    (3, NoteValue::Sixteenth, 0, false),
+    (2, NoteValue::Sixteenth, 0, true),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NotatedNote {
+    pub pitch: Option<SpelledPitch>,
+    pub divisions: u32,
+    pub value: NoteValue,
+    pub dots: u8,
+    pub triplet: bool,
+    pub tuplet_start: bool,
+    pub tuplet_stop: bool,
+    pub tie_start: bool,
+    pub tie_stop: bool,
+    pub lyric: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Measure {
+    pub notes: Vec<NotatedNote>,
+}
+
+pub struct Score {
+    pub title: String,
+    pub key: Key,
+    pub tempo: f32,
+    pub measures: Vec<Measure>,
+}
+
+impl Score {
+    pub fn new(
+        title: String,
+        key: Key,
+        tempo: f32,
+        track: &[ToneAndDuration],
+        lyrics: &str,
+    ) -> Self {
+        let divisions_per_second = tempo / 60.0 * DIVISIONS_PER_QUARTER as f32;
+        let mut syllables = split_lyrics(lyrics).into_iter();
+
+        let mut measures = vec![];
+        let mut notes = vec![];
+        let mut elapsed = 0.0;
+        let mut start = 0;
+        for tone_and_duration in track {
+            elapsed += tone_and_duration.duration;
+            let end = snap_to_barline((elapsed * divisions_per_second).round() as u32);
+            // 1分割の長さは表記できないので、そこまでの長さは次の音符に含める
+            if end < start + MIN_DIVISIONS {
+                continue;
+            }
+            let note_start = std::mem::replace(&mut start, end);
+
+            let pitch = (tone_and_duration.frequency > 0.0).then(|| {
+                let midi_note = frequency_to_midi(tone_and_duration.frequency).round();
+                key.spell(midi_note.clamp(0.0, 127.0) as u8)
+            });
+            let lyric = pitch.as_ref().and_then(|_| syllables.next());
+
+            let pieces = split_into_note_values(note_start, end);
+            let last = pieces.len() - 1;
+            for (i, (divisions, value, dots, triplet)) in pieces.into_iter().enumerate() {
+                notes.push(NotatedNote {
+                    pitch: pitch.clone(),
+                    divisions,
+                    value,
+                    dots,
+                    triplet,
+                    tuplet_start: false,
+                    tuplet_stop: false,
+                    tie_start: pitch.is_some() && i < last,
+                    tie_stop: pitch.is_some() && i > 0,
+                    lyric: if i == 0 { lyric.clone() } else { None },
+                });
+                if measure_length(&notes) == DIVISIONS_PER_MEASURE {
+                    measures.push(Measure {
+                        notes: std::mem::take(&mut notes),
+                    });
+                }
+            }
+        }
+
+        // 最後の小節の不足分は休符で埋める
+        if !notes.is_empty() {
+            let start = measure_length(&notes);
+            for (divisions, value, dots, triplet) in
+                split_into_note_values(start, DIVISIONS_PER_MEASURE)
+            {
+                notes.push(NotatedNote {
+                    pitch: None,
+                    divisions,
+                    value,
+                    dots,
+                    triplet,
+                    tuplet_start: false,
+                    tuplet_stop: false,
+                    tie_start: false,
+                    tie_stop: false,
+                    lyric: None,
+                });
+            }
+            measures.push(Measure { notes });
+        }
+
+        for measure in measures.iter_mut() {
+            mark_tuplets(&mut measure.notes);
+        }
+
+        Self {
+            title,
+            key,
+            tempo,
+            measures,
+        }
+    }
+}
+
+// 英語などは単語ごと、日本語は1文字（拗音は前の仮名とまとめて1音）ごとに1音符に割り当てる。
+// 空白と句読点は歌わないので飛ばす
+fn split_lyrics(lyrics: &str) -> Vec<String> {
+    let mut syllables: Vec<String> = vec![];
+    let mut word = String::new();
+    for c in lyrics.chars() {
+        if c.is_ascii_alphanumeric() || (c == '\'' && !word.is_empty()) {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            syllables.push(std::mem::take(&mut word));
+        }
+        if !c.is_alphanumeric() {
+            continue;
+        }
+        match syllables.last_mut() {
+            Some(last) if is_small_kana(c) && !last.is_ascii() => last.push(c),
+            _ => syllables.push(c.to_string()),
+        }
+    }
+    if !word.is_empty() {
+        syllables.push(word);
+    }
+    syllables
+}
+
+fn is_small_kana(c: char) -> bool {
+    "ぁぃぅぇぉゃゅょゎァィゥェォャュョヮ".contains(c)
+}
+
+// 小節線の1分割手前や後で終わる音符は小節線に揃える
+fn snap_to_barline(position: u32) -> u32 {
+    match position % DIVISIONS_PER_MEASURE {
+        1 => position - 1,
+        offset if offset == DIVISIONS_PER_MEASURE - 1 => position + 1,
+        _ => position,
+    }
+}
+
+fn measure_length(notes: &[NotatedNote]) -> u32 {
+    notes.iter().map(|note| note.divisions).sum()
+}
+
+// 小節線をまたぐ音符は分割し、表記できない長さは表記できる音価の組み合わせにする。
+// 小節線で区切った長さは Score::new で2分割以上に揃えてあり、表記できない1分割が
+// 残らないように音価を選べば、どの長さも分けきれる
+fn split_into_note_values(start: u32, end: u32) -> Vec<(u32, NoteValue, u8, bool)> {
+    let mut pieces = vec![];
+    let mut position = start;
+    while position < end {
+        let measure_end = (position / DIVISIONS_PER_MEASURE + 1) * DIVISIONS_PER_MEASURE;
+        let remaining = end.min(measure_end) - position;
+        let Some(piece) = NOTE_VALUES
+            .iter()
+            .find(|(divisions, ..)| *divisions <= remaining && remaining - divisions != 1)
+            .copied()
+        else {
+            break;
+        };
+        pieces.push(piece);
+        position += piece.0;
+    }
+    pieces
+}
+
+// 連続する三連符を、合計が拍の整数倍になるごとに1つのグループにまとめる
+fn mark_tuplets(notes: &mut [NotatedNote]) {
+    let mut group_length = 0;
+    for i in 0..notes.len() {
+        if !notes[i].triplet {
+            continue;
+        }
+        if group_length == 0 {
+            notes[i].tuplet_start = true;
+        }
+        group_length += notes[i].divisions;
+
+        let next_is_triplet = notes.get(i + 1).is_some_and(|note| note.triplet);
+        if group_length % DIVISIONS_PER_QUARTER == 0 || !next_is_triplet {
+            notes[i].tuplet_stop = true;
+            group_length = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::pitch::Mode;
+
+    fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+        ToneAndDuration {
+            frequency,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_split_into_note_values() {
+        let cases = vec![
+            ((0, 48), vec![(48, NoteValue::Whole, 0, false)]),
+            ((0, 18), vec![(18, NoteValue::Quarter, 1, false)]),
+            (
+                (0, 30),
+                vec![
+                    (24, NoteValue::Half, 0, false),
+                    (6, NoteValue::Eighth, 0, false),
+                ],
+            ),
+            (
+                (36, 60),
+                vec![
+                    (12, NoteValue::Quarter, 0, false),
+                    (12, NoteValue::Quarter, 0, false),
+                ],
+            ),
+            ((0, 16), vec![(16, NoteValue::Half, 0, true)]),
+            // 4 + 1 のように表記できない1分割を残さない
+            (
+                (0, 5),
+                vec![
+                    (3, NoteValue::Sixteenth, 0, false),
+                    (2, NoteValue::Sixteenth, 0, true),
+                ],
+            ),
+            (
+                (0, 13),
+                vec![
+                    (9, NoteValue::Eighth, 1, false),
+                    (4, NoteValue::Eighth, 0, true),
+                ],
+            ),
+        ];
+
+        for ((start, end), expected) in cases {
+            assert_eq!(split_into_note_values(start, end), expected);
+        }
+    }
+
+    #[test]
+    fn test_split_lyrics() {
+        let cases = vec![
+            ("Why Japanese people!?", vec!["Why", "Japanese", "people"]),
+            ("I don't know.", vec!["I", "don't", "know"]),
+            ("きょうは、晴れ。", vec!["きょ", "う", "は", "晴", "れ"]),
+            ("ジョンと Python", vec!["ジョ", "ン", "と", "Python"]),
+            ("", vec![]),
+        ];
+
+        for (lyrics, expected) in cases {
+            assert_eq!(split_lyrics(lyrics), expected);
+        }
+    }
+
+    #[test]
+    fn test_score_english_lyrics() {
+        let track = vec![
+            tone(261.63, 1.0 / 4.0),
+            tone(0.0, 1.0 / 4.0),
+            tone(293.66, 1.0 / 4.0),
+            tone(329.63, 1.0 / 4.0),
+        ];
+        let score = Score::new(
+            "".to_string(),
+            Key::new(0, Mode::Major),
+            240.0,
+            &track,
+            "Why Japanese people!?",
+        );
+
+        let lyrics: Vec<Option<String>> = score.measures[0]
+            .notes
+            .iter()
+            .map(|note| note.lyric.clone())
+            .collect();
+        assert_eq!(
+            lyrics,
+            vec![
+                Some("Why".to_string()),
+                None,
+                Some("Japanese".to_string()),
+                Some("people".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_triplets() {
+        // generate_track_katakana の1小節目と3小節目
+        let track = vec![
+            tone(392.00, 1.0 / 3.0),
+            tone(440.00, 1.0 / 6.0),
+            tone(493.88, 1.0 / 2.0),
+            tone(392.00, 1.0 / 6.0),
+            tone(440.00, 1.0 / 6.0),
+            tone(493.88, 1.0 / 6.0),
+            tone(523.25, 1.0 / 2.0),
+        ];
+        let score = Score::new(
+            "カタカナ".to_string(),
+            Key::new(7, Mode::Major),
+            240.0,
+            &track,
+            "",
+        );
+
+        assert_eq!(score.measures.len(), 2);
+        let first: Vec<(u32, bool, bool, bool)> = score.measures[0]
+            .notes
+            .iter()
+            .map(|note| {
+                (
+                    note.divisions,
+                    note.triplet,
+                    note.tuplet_start,
+                    note.tuplet_stop,
+                )
+            })
+            .collect();
+        assert_eq!(
+            first,
+            vec![
+                (16, true, true, false),
+                (8, true, false, true),
+                (24, false, false, false)
+            ]
+        );
+        let second: Vec<(u32, bool, bool)> = score.measures[1]
+            .notes
+            .iter()
+            .map(|note| (note.divisions, note.tuplet_start, note.tuplet_stop))
+            .collect();
+        assert_eq!(
+            second,
+            vec![
+                (8, true, false),
+                (8, false, false),
+                (8, false, true),
+                (24, false, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_score_one_division_remainder() {
+        // 240 BPM では 1/48 秒が1分割になる
+        let track = vec![
+            tone(261.63, 1.0 / 48.0),
+            tone(293.66, 48.0 / 48.0),
+            tone(329.63, 44.0 / 48.0),
+            tone(349.23, 1.0 / 48.0),
+            tone(392.00, 2.0 / 48.0),
+        ];
+        let score = Score::new("".to_string(), Key::new(0, Mode::Major), 240.0, &track, "");
+
+        let notes: Vec<(Option<char>, u32)> = score
+            .measures
+            .iter()
+            .flat_map(|measure| &measure.notes)
+            .map(|note| (note.pitch.as_ref().map(|pitch| pitch.step), note.divisions))
+            .collect();
+        // 1分割の音符は次の音符に含め、小節線の1分割後で終わる音符は小節線に揃える
+        assert_eq!(
+            notes,
+            vec![
+                (Some('D'), 48),
+                (Some('E'), 36),
+                (Some('E'), 9),
+                (Some('G'), 3)
+            ]
+        );
+        for note in score.measures.iter().flat_map(|measure| &measure.notes) {
+            assert!(NOTE_VALUES.contains(&(note.divisions, note.value, note.dots, note.triplet)));
+        }
+    }
+
+    #[test]
+    fn test_score_ties_across_barline() {
+        let track = vec![tone(261.63, 3.0 / 4.0), tone(293.66, 1.0 / 2.0)];
+        let score = Score::new(
+            "".to_string(),
+            Key::new(0, Mode::Major),
+            240.0,
+            &track,
+            "あい",
+        );
+
+        assert_eq!(score.measures.len(), 2);
+        let tied = &score.measures[0].notes[1];
+        assert_eq!(tied.pitch.as_ref().unwrap().step, 'D');
+        assert_eq!(
+            (tied.divisions, tied.tie_start, tied.tie_stop),
+            (12, true, false)
+        );
+        assert_eq!(tied.lyric, Some("い".to_string()));
+
+        let continued = &score.measures[1].notes[0];
+        assert_eq!(
+            (continued.divisions, continued.tie_start, continued.tie_stop),
+            (12, false, true)
+        );
+        assert_eq!(continued.lyric, None);
+
+        // 最後の小節の残りは休符
+        let rest = &score.measures[1].notes[1];
+        assert_eq!((rest.pitch.clone(), rest.divisions), (None, 36));
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+
+use super::portable_math::{exp2, log2};
+
+const A4_FREQUENCY: f32 = 440.0;
+const A4_MIDI_NOTE: f32 = 69.0;
+
+pub fn frequency_to_midi(frequency: f32) -> f32 {
+    A4_MIDI_NOTE + 12.0 * log2((frequency / A4_FREQUENCY) as f64) as f32
+}
+
+pub fn midi_to_frequency(note: f32) -> f32 {
+    (A4_FREQUENCY as f64 * exp2((note - A4_MIDI_NOTE) as f64 / 12.0)) as f32
+}
+
+// 半音単位で音程を動かす。周波数を経由して MIDI ノート番号に戻さないので誤差が増えない
+pub fn shift_semitones(frequency: f32, semitones: i32) -> f32 {
+    (frequency as f64 * exp2(semitones as f64 / 12.0)) as f32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    pub tonic: u8,
+    pub mode: Mode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpelledPitch {
+    pub step: char,
+    pub alter: i8,
+    pub octave: i8,
+}
+
+impl Key {
+    pub fn new(tonic: u8, mode: Mode) -> Self {
+        Self {
+            tonic: tonic % 12,
+            mode,
+        }
+    }
+
+    // 調号のシャープ（正）・フラット（負）の数
+    pub fn fifths(&self) -> i8 {
+        let major_tonic = match self.mode {
+            Mode::Major => self.tonic,
+            Mode::Minor => (self.tonic + 3) % 12,
+        };
+        let fifths = (major_tonic as i8 * 7).rem_euclid(12);
+        if fifths > 6 {
+            fifths - 12
+        } else {
+            fifths
+        }
+    }
+
+    // 調号によって音名に付く変化記号
+    pub fn signature_alter(&self, step: char) -> i8 {
+        let fifths = self.fifths();
+        let sharps = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+        match sharps.iter().position(|&sharp| sharp == step) {
+            Some(i) if (i as i8) < fifths => 1,
+            Some(i) if (6 - i as i8) < -fifths => -1,
+            _ => 0,
+        }
+    }
+
+    pub fn spell(&self, midi_note: u8) -> SpelledPitch {
+        // 五度圏上で調の中心に最も近い綴りを選ぶ（F = -1, C = 0, G = 1, ...）
+        let center = self.fifths() as f32 + 1.5;
+        let position = (midi_note as i32 % 12 * 7).rem_euclid(12);
+        let position = [position, position - 12]
+            .into_iter()
+            .min_by(|a, b| {
+                (*a as f32 - center)
+                    .abs()
+                    .total_cmp(&(*b as f32 - center).abs())
+            })
+            .unwrap();
+
+        let step = ['F', 'C', 'G', 'D', 'A', 'E', 'B'][(position + 1).rem_euclid(7) as usize];
+        let alter = (position + 1).div_euclid(7) as i8;
+        let natural = midi_note as i32 - alter as i32;
+        SpelledPitch {
+            step,
+            alter,
+            octave: (natural.div_euclid(12) - 1) as i8,
+        }
+    }
+
+    // この調の音を別の調の同じ音度へ移す。主音は近い方へ動かし、
+    // 長調と短調の間では3・6・7度を半音ずらす
+    pub fn transpose(&self, to: &Key, frequency: f32) -> f32 {
+        if frequency <= 0.0 {
+            return frequency;
+        }
+        let note = frequency_to_midi(frequency).round() as i32;
+        let degree = (note - self.tonic as i32).rem_euclid(12);
+        let alter = match (self.mode, to.mode, degree) {
+            (Mode::Major, Mode::Minor, 4 | 9 | 11) => -1,
+            (Mode::Minor, Mode::Major, 3 | 8 | 10) => 1,
+            _ => 0,
+        };
+        let shift = (to.tonic as i32 - self.tonic as i32 + 5).rem_euclid(12) - 5;
+        shift_semitones(frequency, shift + alter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency_to_midi() {
+        let cases = vec![(440.0, 69.0), (261.63, 60.0), (523.25, 72.0), (392.00, 67.0)];
+
+        for (frequency, expected) in cases {
+            assert!((frequency_to_midi(frequency) - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_midi_to_frequency() {
+        let cases = vec![(69.0, 440.0), (60.0, 261.63), (72.0, 523.25), (67.0, 392.00)];
+
+        for (note, expected) in cases {
+            assert!((midi_to_frequency(note) - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_key_fifths() {
+        let cases = vec![
+            (Key::new(0, Mode::Major), 0),
+            (Key::new(7, Mode::Major), 1),
+            (Key::new(5, Mode::Major), -1),
+            (Key::new(3, Mode::Major), -3),
+            (Key::new(9, Mode::Minor), 0),
+            (Key::new(4, Mode::Minor), 1),
+            (Key::new(2, Mode::Minor), -1),
+        ];
+
+        for (key, expected) in cases {
+            assert_eq!(key.fifths(), expected);
+        }
+    }
+
+    #[test]
+    fn test_key_spell() {
+        let spelled = |step, alter, octave| SpelledPitch {
+            step,
+            alter,
+            octave,
+        };
+        let cases = vec![
+            (Key::new(0, Mode::Major), 60, spelled('C', 0, 4)),
+            (Key::new(0, Mode::Major), 70, spelled('B', -1, 4)),
+            (Key::new(0, Mode::Major), 68, spelled('A', -1, 4)),
+            (Key::new(0, Mode::Major), 66, spelled('F', 1, 4)),
+            (Key::new(7, Mode::Major), 78, spelled('F', 1, 5)),
+            (Key::new(5, Mode::Major), 70, spelled('B', -1, 4)),
+            (Key::new(2, Mode::Major), 61, spelled('C', 1, 4)),
+            (Key::new(3, Mode::Major), 68, spelled('A', -1, 4)),
+            (Key::new(6, Mode::Major), 71, spelled('B', 0, 4)),
+            (Key::new(6, Mode::Major), 65, spelled('E', 1, 4)),
+        ];
+
+        for (key, midi_note, expected) in cases {
+            assert_eq!(key.spell(midi_note), expected);
+        }
+    }
+
+    #[test]
+    fn test_key_signature_alter() {
+        let cases = vec![
+            (Key::new(0, Mode::Major), 'F', 0),
+            (Key::new(7, Mode::Major), 'F', 1),
+            (Key::new(7, Mode::Major), 'C', 0),
+            (Key::new(2, Mode::Major), 'C', 1),
+            (Key::new(5, Mode::Major), 'B', -1),
+            (Key::new(5, Mode::Major), 'E', 0),
+            (Key::new(3, Mode::Major), 'A', -1),
+            (Key::new(3, Mode::Major), 'D', 0),
+        ];
+
+        for (key, step, expected) in cases {
+            assert_eq!(key.signature_alter(step), expected);
+        }
+    }
+
+    #[test]
+    fn test_key_transpose() {
+        let c_major = Key::new(0, Mode::Major);
+        let cases = vec![
+            ((c_major, Key::new(0, Mode::Major), 440.0), 440.0),
+            ((c_major, Key::new(2, Mode::Major), 261.63), 293.66),
+            ((c_major, Key::new(7, Mode::Major), 261.63), 196.00),
+            ((c_major, Key::new(0, Mode::Minor), 329.63), 311.13),
+            ((c_major, Key::new(0, Mode::Minor), 392.00), 392.00),
+            ((c_major, Key::new(9, Mode::Minor), 329.63), 261.63),
+            ((Key::new(9, Mode::Minor), c_major, 261.63), 329.63),
+            ((c_major, Key::new(5, Mode::Major), 0.0), 0.0),
+        ];
+
+        for ((from, to, frequency), expected) in cases {
+            assert!((from.transpose(&to, frequency) - expected).abs() < 0.01);
+        }
+    }
+}
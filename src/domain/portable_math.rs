@@ -0,0 +1,159 @@
+use std::{
+    f64::consts::{LN_2, PI, TAU},
+    sync::OnceLock,
+};
+
+// 決定的モード
+// 音の生成は四則演算だけで計算し、sin や pow などの libm の関数を使わない。
+// libm の実装は OS や WASM ランタイムごとに最後の桁が異なりうるが、四則演算は
+// IEEE 754 で結果が一意に決まるので、同じ入力と設定からはどこでも同じサンプルになる。
+// 乱数はアルゴリズムが固定の ChaCha8 だけを使う。humanize のシードはテキストの
+// FNV-1a ハッシュから、WAV や FLAC のディザのシードは固定値から決める。
+
+const SINE_TABLE_SIZE: usize = 8192;
+
+static SINE_TABLE: OnceLock<Vec<f64>> = OnceLock::new();
+
+// phase は周期単位（1.0 で一周）。テーブルを線形補間する
+pub fn sine(phase: f64) -> f64 {
+    let table = SINE_TABLE.get_or_init(|| {
+        (0..=SINE_TABLE_SIZE)
+            .map(|i| taylor_sine(i as f64 / SINE_TABLE_SIZE as f64))
+            .collect()
+    });
+    let position = phase.rem_euclid(1.0) * SINE_TABLE_SIZE as f64;
+    // ごく小さい負の位相では rem_euclid が 1.0 ちょうどを返すので、最後の区間に収める
+    let index = (position as usize).min(SINE_TABLE_SIZE - 1);
+    let fraction = position - index as f64;
+    table[index] + (table[index + 1] - table[index]) * fraction
+}
+
+fn taylor_sine(phase: f64) -> f64 {
+    // [-π/2, π/2] に折り返してから級数で求める
+    let mut x = (phase - phase.round()) * TAU;
+    if x > PI / 2.0 {
+        x = PI - x;
+    } else if x < -PI / 2.0 {
+        x = -PI - x;
+    }
+    let square = x * x;
+    let mut term = x;
+    let mut sum = x;
+    for n in 1..=12 {
+        term = -term * square / ((2 * n) * (2 * n + 1)) as f64;
+        sum += term;
+    }
+    // π/2 付近で丸め誤差により 1 をわずかに超えるのを防ぐ
+    sum.clamp(-1.0, 1.0)
+}
+
+pub fn exp2(x: f64) -> f64 {
+    let integer = x.floor();
+    if integer < -1022.0 {
+        return 0.0;
+    }
+    if integer > 1023.0 {
+        return f64::INFINITY;
+    }
+    // 2^x = 2^整数部 × e^(小数部 × ln2)
+    let y = (x - integer) * LN_2;
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for n in 1..=20 {
+        term = term * y / n as f64;
+        sum += term;
+    }
+    sum * f64::from_bits(((integer as i64 + 1023) as u64) << 52)
+}
+
+pub fn log2(x: f64) -> f64 {
+    if x.is_nan() || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if x.is_infinite() || !x.is_normal() {
+        return x.log2();
+    }
+    // x = m × 2^e（√½ ≤ m < √2）に分けて、ln m = 2 atanh((m - 1) / (m + 1)) を級数で求める
+    let bits = x.to_bits();
+    let mut exponent = ((bits >> 52) & 0x7FF) as i64 - 1023;
+    let mut mantissa = f64::from_bits((bits & 0x000F_FFFF_FFFF_FFFF) | (1023 << 52));
+    if mantissa > std::f64::consts::SQRT_2 {
+        mantissa /= 2.0;
+        exponent += 1;
+    }
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let square = s * s;
+    let mut power = s;
+    let mut sum = 0.0;
+    for n in 0..12 {
+        sum += power / (2 * n + 1) as f64;
+        power *= square;
+    }
+    exponent as f64 + 2.0 * sum / LN_2
+}
+
+pub fn db_to_amplitude(db: f64) -> f64 {
+    exp2(db / 20.0 * std::f64::consts::LOG2_10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine() {
+        for i in 0..1000 {
+            let phase = i as f64 / 997.0;
+            assert!((sine(phase) - (phase * TAU).sin()).abs() < 1e-7);
+        }
+        assert_eq!(sine(0.0), 0.0);
+        assert_eq!(sine(0.25), 1.0);
+        assert_eq!(sine(1.25), 1.0);
+        assert_eq!(sine(-0.75), 1.0);
+        assert!(sine(-1e-20).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_exp2() {
+        let cases = vec![
+            0.0,
+            1.0,
+            -1.0,
+            0.5,
+            10.25,
+            -3.75,
+            1.0 / 12.0,
+            1000.0,
+            -1000.0,
+        ];
+
+        for x in cases {
+            assert!((exp2(x) / x.exp2() - 1.0).abs() < 1e-14);
+        }
+        assert_eq!(exp2(3.0), 8.0);
+        assert_eq!(exp2(-2000.0), 0.0);
+    }
+
+    #[test]
+    fn test_log2() {
+        let cases = vec![1.0, 2.0, 0.5, 3.0, 440.0, 261.63, 1e-300, 1e300];
+
+        for x in cases {
+            assert!((log2(x) - x.log2()).abs() < 1e-13);
+        }
+        assert_eq!(log2(8.0), 3.0);
+        assert!(log2(-1.0).is_nan());
+    }
+
+    #[test]
+    fn test_db_to_amplitude() {
+        let cases = vec![(0.0, 1.0), (-20.0, 0.1), (20.0, 10.0), (-6.0, 0.501187)];
+
+        for (db, expected) in cases {
+            assert!((db_to_amplitude(db) - expected).abs() < 1e-6);
+        }
+    }
+}
@@ -1,49 +1,76 @@
-use dasp::{signal, Signal};
+use super::{
+    instrument::{Instrument, Sine},
+    renderer::Renderer,
+};
 
 pub struct PureTones {
-    pub samples: Vec<i16>,
+    pub samples: Vec<f64>,
 }
 
 impl PureTones {
     pub fn new(sample_rate: u32, track: Vec<ToneAndDuration>) -> Self {
-        let total_frames = (sample_rate as f32
-            * track
-                .iter()
-                .map(|tone_and_duration| tone_and_duration.duration)
-                .sum::<f32>()) as usize;
-
-        let fade_samples = (sample_rate as f32 * 0.01) as usize;
-        let signal = signal::from_iter(track.iter().flat_map(|tone_and_duration| {
-            let duration_samples = (sample_rate as f32 * tone_and_duration.duration) as usize;
-
-            signal::rate(sample_rate as f64)
-                .const_hz(tone_and_duration.frequency.into())
-                .sine()
-                .take((sample_rate as f32 * tone_and_duration.duration) as usize)
-                .enumerate()
-                .map(move |(i, sample)| {
-                    let fade_factor = if i < fade_samples {
-                        i as f64 / fade_samples as f64
-                    } else if i >= duration_samples - fade_samples {
-                        (duration_samples - i) as f64 / fade_samples as f64
-                    } else {
-                        1.0
-                    };
-                    sample * fade_factor
-                })
-        }));
-
-        let samples: Vec<i16> = signal
-            .take(total_frames)
-            .map(|sample| (sample * i16::MAX as f64) as i16)
-            .collect();
+        Self::with_instrument(sample_rate, track, &mut Sine)
+    }
+
+    pub fn with_instrument(
+        sample_rate: u32,
+        track: Vec<ToneAndDuration>,
+        instrument: &mut impl Instrument,
+    ) -> Self {
+        let samples = Renderer::new(sample_rate, track, instrument).collect();
 
         Self { samples }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct ToneAndDuration {
     pub frequency: f32,
     pub duration: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn samples_hash(samples: &[f64]) -> String {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    // sin などを使わずに計算しているので、WASM でもネイティブでも同じハッシュになる
+    #[test]
+    fn test_samples_hash() {
+        let track: Vec<ToneAndDuration> = [261.63, 293.66, 329.63, 0.0, 392.00, 440.00, 493.88]
+            .iter()
+            .map(|&frequency| ToneAndDuration {
+                frequency,
+                duration: 1.0 / 3.0,
+            })
+            .collect();
+        let cases = vec![
+            (
+                8000,
+                "736d44a57a8f4fb7ab8d2c460ba6c3b24bf54763969389a946a248d2d94e95cf",
+            ),
+            (
+                44100,
+                "2540e04adfd4cfb9e73201fbe60d772febf6201dd40c91dd68ce4439f1b9da89",
+            ),
+        ];
+
+        for (sample_rate, expected) in cases {
+            let pure_tones = PureTones::new(sample_rate, track.clone());
+            assert_eq!(
+                samples_hash(&pure_tones.samples),
+                expected,
+                "{}",
+                sample_rate
+            );
+        }
+    }
+}
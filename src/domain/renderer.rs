@@ -0,0 +1,389 @@
+use super::{instrument::Instrument, pure_tone::ToneAndDuration};
+
+const FADE_SECONDS: f32 = 0.01;
+
+// 曲全体を保持せず、必要になった音符だけを描画しながらサンプルを返す
+pub struct Renderer<I> {
+    sample_rate: u32,
+    notes: std::iter::Peekable<std::vec::IntoIter<ScheduledNote>>,
+    instrument: I,
+    articulation: Articulation,
+    velocities: Option<std::vec::IntoIter<f32>>,
+    previous_sounding: bool,
+    previous_velocity: f32,
+    note: std::vec::IntoIter<f64>,
+    remaining: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Articulation {
+    // 音符ごとにフェードをかけて区切る
+    Detached,
+    // 音が続く間はフェードをかけずにつなげる。LegatoSine のような位相が連続する楽器向け
+    Legato,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScheduledNote {
+    pub frequency: f32,
+    pub start: usize,
+    pub frames: usize,
+}
+
+impl<I: Instrument> Renderer<I> {
+    pub fn new(sample_rate: u32, track: Vec<ToneAndDuration>, instrument: I) -> Self {
+        Self {
+            sample_rate,
+            notes: schedule(sample_rate, &track).into_iter().peekable(),
+            instrument,
+            articulation: Articulation::Detached,
+            velocities: None,
+            previous_sounding: false,
+            previous_velocity: 1.0,
+            note: vec![].into_iter(),
+            remaining: total_frames(sample_rate, &track),
+        }
+    }
+
+    pub fn with_articulation(mut self, articulation: Articulation) -> Self {
+        self.articulation = articulation;
+        self
+    }
+
+    // 音符ごとの強さ（0〜1）で振幅を変える。足りない分は 1.0 として扱う
+    pub fn with_velocities(mut self, velocities: Vec<f32>) -> Self {
+        self.velocities = Some(velocities.into_iter());
+        self
+    }
+
+    fn render_next_note(&mut self) -> Option<Vec<f64>> {
+        let scheduled = self.notes.next()?;
+        let mut note =
+            self.instrument
+                .render_note(self.sample_rate, scheduled.frequency, scheduled.frames);
+
+        let sounding = scheduled.frequency > 0.0;
+        let next_sounding = self.notes.peek().is_some_and(|next| next.frequency > 0.0);
+        let fade_frames = (self.sample_rate as f32 * FADE_SECONDS) as usize;
+        let (fade_in, fade_out) = match self.articulation {
+            Articulation::Detached => (fade_frames, fade_frames),
+            // 休符や曲の端に接する側だけフェードする
+            Articulation::Legato => (
+                if sounding && self.previous_sounding {
+                    0
+                } else {
+                    fade_frames
+                },
+                if sounding && next_sounding {
+                    0
+                } else {
+                    fade_frames
+                },
+            ),
+        };
+        apply_fade(&mut note, fade_in, fade_out);
+        if let Some(velocities) = self.velocities.as_mut() {
+            let velocity = velocities.next().unwrap_or(1.0);
+            // フェードせずにつながる音は、強さが段差にならないよう前の音の強さから変える
+            let from = if fade_in == 0 {
+                self.previous_velocity
+            } else {
+                velocity
+            };
+            apply_velocity(&mut note, from, velocity, fade_frames);
+            self.previous_velocity = velocity;
+        }
+        self.previous_sounding = sounding;
+        Some(note)
+    }
+}
+
+impl<I: Instrument> Iterator for Renderer<I> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        loop {
+            if let Some(sample) = self.note.next() {
+                return Some(sample);
+            }
+            match self.render_next_note() {
+                Some(note) => self.note = note.into_iter(),
+                // 音符が足りなければ無音で埋める
+                None => return Some(0.0),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<I: Instrument> ExactSizeIterator for Renderer<I> {}
+
+// 各音符の開始位置を累積時間から求める。音符ごとに長さを丸めると誤差が蓄積して
+// 曲の長さやパート間の位置がずれるため、どのパートでも同じ規則で絶対位置を決める
+pub fn schedule(sample_rate: u32, track: &[ToneAndDuration]) -> Vec<ScheduledNote> {
+    let mut elapsed = 0.0;
+    track
+        .iter()
+        .map(|tone_and_duration| {
+            let start = frame_at(sample_rate, elapsed);
+            elapsed += tone_and_duration.duration as f64;
+            ScheduledNote {
+                frequency: tone_and_duration.frequency,
+                start,
+                frames: frame_at(sample_rate, elapsed) - start,
+            }
+        })
+        .collect()
+}
+
+pub fn total_frames(sample_rate: u32, track: &[ToneAndDuration]) -> usize {
+    frame_at(sample_rate, total_seconds(track))
+}
+
+fn total_seconds(track: &[ToneAndDuration]) -> f64 {
+    track
+        .iter()
+        .map(|tone_and_duration| tone_and_duration.duration as f64)
+        .sum()
+}
+
+fn frame_at(sample_rate: u32, seconds: f64) -> usize {
+    (seconds * sample_rate as f64).round() as usize
+}
+
+// 音符の始めと終わりにフェードをかけてクリックノイズを防ぐ
+fn apply_fade(note: &mut [f64], fade_in: usize, fade_out: usize) {
+    let frames = note.len();
+    for (i, sample) in note.iter_mut().enumerate() {
+        let fade_factor = if i < fade_in {
+            i as f64 / fade_in as f64
+        } else if i >= frames.saturating_sub(fade_out) {
+            (frames - i) as f64 / fade_out as f64
+        } else {
+            1.0
+        };
+        *sample *= fade_factor;
+    }
+}
+
+// 先頭の ramp フレームで from から to へ強さを変える
+fn apply_velocity(note: &mut [f64], from: f32, to: f32, ramp: usize) {
+    let ramp = ramp.min(note.len());
+    for (i, sample) in note.iter_mut().enumerate() {
+        let velocity = if i < ramp {
+            from as f64 + (to - from) as f64 * i as f64 / ramp as f64
+        } else {
+            to as f64
+        };
+        *sample *= velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter {
+        notes: usize,
+        frames: Vec<usize>,
+    }
+
+    impl Counter {
+        fn new() -> Self {
+            Self {
+                notes: 0,
+                frames: vec![],
+            }
+        }
+    }
+
+    impl Instrument for Counter {
+        fn render_note(&mut self, _sample_rate: u32, _frequency: f32, frames: usize) -> Vec<f64> {
+            self.notes += 1;
+            self.frames.push(frames);
+            vec![1.0; frames]
+        }
+    }
+
+    fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+        ToneAndDuration {
+            frequency,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_apply_fade() {
+        let cases = vec![
+            ((2, 2), vec![0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5]),
+            ((0, 2), vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5]),
+            ((2, 0), vec![0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0]),
+        ];
+
+        for ((fade_in, fade_out), expected) in cases {
+            let mut note = vec![1.0; 8];
+            apply_fade(&mut note, fade_in, fade_out);
+            assert_eq!(note, expected);
+        }
+    }
+
+    #[test]
+    fn test_apply_fade_short_note() {
+        let mut note = vec![1.0; 3];
+        apply_fade(&mut note, 4, 4);
+
+        assert_eq!(note, vec![0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn test_renderer_length() {
+        let track = vec![tone(440.0, 0.5), tone(0.0, 0.25), tone(880.0, 0.25)];
+        let renderer = Renderer::new(1000, track, Counter::new());
+
+        assert_eq!(renderer.len(), 1000);
+        assert_eq!(renderer.count(), 1000);
+    }
+
+    #[test]
+    fn test_renderer_without_drift() {
+        // 0.7 秒（f32）× 1000Hz は 699.99994 なので、切り捨てると1音ごとに1フレームずつずれる
+        let track: Vec<ToneAndDuration> = (0..100).map(|_| tone(440.0, 0.7)).collect();
+        let mut counter = Counter::new();
+        let samples = Renderer::new(1000, track, &mut counter).count();
+
+        assert_eq!(samples, 70000);
+        assert!(counter.frames.iter().all(|&frames| frames == 700));
+    }
+
+    #[test]
+    fn test_schedule() {
+        let track = vec![
+            tone(392.0, 1.0 / 3.0),
+            tone(440.0, 1.0 / 3.0),
+            tone(493.88, 1.0 / 3.0),
+        ];
+
+        assert_eq!(
+            schedule(44100, &track),
+            vec![
+                ScheduledNote {
+                    frequency: 392.0,
+                    start: 0,
+                    frames: 14700,
+                },
+                ScheduledNote {
+                    frequency: 440.0,
+                    start: 14700,
+                    frames: 14700,
+                },
+                ScheduledNote {
+                    frequency: 493.88,
+                    start: 29400,
+                    frames: 14700,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_voices_stay_aligned() {
+        // 三連符のパートと全音符のパートが同じ位置で揃う
+        let triplets: Vec<ToneAndDuration> = (0..30).map(|_| tone(440.0, 1.0 / 3.0)).collect();
+        let whole_notes: Vec<ToneAndDuration> = (0..10).map(|_| tone(220.0, 1.0)).collect();
+
+        assert_eq!(total_frames(44100, &triplets), 441000);
+        assert_eq!(
+            total_frames(44100, &triplets),
+            total_frames(44100, &whole_notes)
+        );
+        let triplet_starts: Vec<usize> = schedule(44100, &triplets)
+            .iter()
+            .step_by(3)
+            .map(|note| note.start)
+            .collect();
+        let whole_note_starts: Vec<usize> = schedule(44100, &whole_notes)
+            .iter()
+            .map(|note| note.start)
+            .collect();
+        assert_eq!(triplet_starts, whole_note_starts);
+        assert_eq!(
+            Renderer::new(44100, triplets, Counter::new()).count(),
+            441000
+        );
+    }
+
+    #[test]
+    fn test_renderer_articulation() {
+        let track = vec![
+            tone(440.0, 0.1),
+            tone(880.0, 0.1),
+            tone(0.0, 0.1),
+            tone(440.0, 0.1),
+        ];
+        let detached: Vec<f64> = Renderer::new(1000, track.clone(), Counter::new()).collect();
+        let legato: Vec<f64> = Renderer::new(1000, track, Counter::new())
+            .with_articulation(Articulation::Legato)
+            .collect();
+
+        // 1音目と2音目の境界
+        assert_eq!(&detached[99..101], &[0.1, 0.0]);
+        assert_eq!(&legato[99..101], &[1.0, 1.0]);
+        // 曲の端と休符の前後はどちらもフェードする
+        for samples in [&detached, &legato] {
+            assert_eq!(samples[0], 0.0);
+            assert_eq!(samples[199], 0.1);
+            assert_eq!(samples[300], 0.0);
+            assert_eq!(samples[399], 0.1);
+        }
+    }
+
+    #[test]
+    fn test_renderer_velocities() {
+        let track = vec![tone(440.0, 0.1), tone(440.0, 0.1), tone(440.0, 0.1)];
+        let samples: Vec<f64> = Renderer::new(1000, track, Counter::new())
+            .with_velocities(vec![0.5, 0.25])
+            .collect();
+
+        assert_eq!(samples[50], 0.5);
+        assert_eq!(samples[150], 0.25);
+        assert_eq!(samples[250], 1.0);
+    }
+
+    #[test]
+    fn test_renderer_legato_velocities() {
+        let track = vec![tone(440.0, 0.1), tone(440.0, 0.1), tone(440.0, 0.1)];
+        let samples: Vec<f64> = Renderer::new(1000, track, Counter::new())
+            .with_articulation(Articulation::Legato)
+            .with_velocities(vec![1.0, 0.25, 1.0])
+            .collect();
+
+        // 音の境界で強さが一度に変わらず、フェードの長さ（10フレーム）をかけて変わる
+        let max_jump = samples[50..250]
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0, f64::max);
+        assert!(max_jump <= 0.075 + 1e-9, "{}", max_jump);
+        assert_eq!(samples[150], 0.25);
+        assert_eq!(samples[250], 1.0);
+    }
+
+    #[test]
+    fn test_renderer_is_lazy() {
+        // 1万小節分の音符があっても、先頭を取り出す間は最初の音符しか描画しない
+        let track: Vec<ToneAndDuration> = (0..10000).map(|_| tone(440.0, 1.0)).collect();
+        let mut counter = Counter::new();
+        let samples: Vec<f64> = Renderer::new(44100, track, &mut counter)
+            .take(100)
+            .collect();
+
+        assert_eq!(samples.len(), 100);
+        assert_eq!(counter.notes, 1);
+    }
+}
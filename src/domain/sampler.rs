@@ -0,0 +1,179 @@
+use super::{instrument::Instrument, pitch::frequency_to_midi};
+
+pub struct Sample {
+    pub frames: Vec<f32>,
+    pub sample_rate: u32,
+    pub root_frequency: f32,
+    pub loop_points: Option<(usize, usize)>,
+}
+
+pub struct SampleZone {
+    pub sample: Sample,
+    pub low_key: u8,
+    pub high_key: u8,
+}
+
+pub struct Sampler {
+    pub zones: Vec<SampleZone>,
+}
+
+impl Sampler {
+    pub fn new(zones: Vec<SampleZone>) -> Self {
+        Self { zones }
+    }
+
+    pub fn single_shot(sample: Sample) -> Self {
+        Self::new(vec![SampleZone {
+            sample,
+            low_key: 0,
+            high_key: 127,
+        }])
+    }
+
+    fn find_zone(&self, frequency: f32) -> Option<&SampleZone> {
+        let key = frequency_to_midi(frequency).round().clamp(0.0, 127.0) as u8;
+
+        // 範囲外の音は最も近いゾーンのサンプルで代用する
+        self.zones.iter().min_by_key(|zone| {
+            zone.low_key
+                .saturating_sub(key)
+                .max(key.saturating_sub(zone.high_key))
+        })
+    }
+}
+
+impl Instrument for Sampler {
+    fn render_note(&mut self, sample_rate: u32, frequency: f32, frames: usize) -> Vec<f64> {
+        // 休符ではサンプルを鳴らさない
+        if frequency <= 0.0 {
+            return vec![0.0; frames];
+        }
+        let Some(zone) = self.find_zone(frequency) else {
+            return vec![0.0; frames];
+        };
+        let sample = &zone.sample;
+        let step = (frequency as f64 / sample.root_frequency as f64)
+            * (sample.sample_rate as f64 / sample_rate as f64);
+
+        (0..frames)
+            .map(|i| {
+                let position = match sample.loop_points {
+                    Some((start, end)) if end > start && i as f64 * step >= end as f64 => {
+                        let length = (end - start) as f64;
+                        start as f64 + (i as f64 * step - start as f64) % length
+                    }
+                    _ => i as f64 * step,
+                };
+                let index = position as usize;
+                let fraction = position - index as f64;
+                match (sample.frames.get(index), sample.frames.get(index + 1)) {
+                    (Some(&current), Some(&next)) => {
+                        current as f64 + (next as f64 - current as f64) * fraction
+                    }
+                    (Some(&current), None) => current as f64 * (1.0 - fraction),
+                    _ => 0.0,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(len: usize, sample_rate: u32, root_frequency: f32) -> Sample {
+        Sample {
+            frames: (0..len).map(|i| i as f32 / len as f32).collect(),
+            sample_rate,
+            root_frequency,
+            loop_points: None,
+        }
+    }
+
+    #[test]
+    fn test_render_note_at_root_frequency() {
+        let mut sampler = Sampler::single_shot(ramp(8, 8000, 440.0));
+        let rendered = sampler.render_note(8000, 440.0, 8);
+
+        let expected: Vec<f64> = (0..8).map(|i| i as f32 as f64 / 8.0).collect();
+        for (actual, expected) in rendered.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_render_note_pitch_shift() {
+        let mut sampler = Sampler::single_shot(ramp(8, 8000, 220.0));
+        let rendered = sampler.render_note(8000, 440.0, 6);
+
+        // 1オクターブ上は2倍速で再生され、サンプルが尽きたら無音になる
+        let expected = vec![0.0, 0.25, 0.5, 0.75, 0.0, 0.0];
+        for (actual, expected) in rendered.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_render_note_resamples_to_output_rate() {
+        let mut sampler = Sampler::single_shot(ramp(8, 16000, 440.0));
+        let rendered = sampler.render_note(8000, 440.0, 4);
+
+        let expected = vec![0.0, 0.25, 0.5, 0.75];
+        for (actual, expected) in rendered.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_render_note_with_loop() {
+        let mut sample = ramp(8, 8000, 440.0);
+        sample.loop_points = Some((4, 8));
+        let mut sampler = Sampler::single_shot(sample);
+        let rendered = sampler.render_note(8000, 440.0, 12);
+
+        let expected = vec![
+            0.0, 0.125, 0.25, 0.375, 0.5, 0.625, 0.75, 0.875, 0.5, 0.625, 0.75, 0.875,
+        ];
+        for (actual, expected) in rendered.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_render_rest() {
+        let mut sample = ramp(8, 8000, 440.0);
+        sample.frames[0] = 0.5;
+        let mut sampler = Sampler::single_shot(sample);
+
+        assert_eq!(sampler.render_note(8000, 0.0, 4), vec![0.0; 4]);
+    }
+
+    #[test]
+    fn test_find_zone() {
+        let zones = vec![
+            SampleZone {
+                sample: ramp(4, 8000, 261.63),
+                low_key: 48,
+                high_key: 63,
+            },
+            SampleZone {
+                sample: ramp(4, 8000, 523.25),
+                low_key: 64,
+                high_key: 79,
+            },
+        ];
+        let sampler = Sampler::new(zones);
+        let cases = vec![
+            (261.63, 261.63),
+            (329.63, 523.25),
+            (110.00, 261.63),
+            (1760.00, 523.25),
+        ];
+
+        for (frequency, expected) in cases {
+            let zone = sampler.find_zone(frequency).unwrap();
+            assert_eq!(zone.sample.root_frequency, expected);
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use std::cmp::Ordering;
 
-use super::{pure_tone::ToneAndDuration, text_analyzer::TextAnalyzer};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq)]
-enum TrackType {
+use super::{
+    form::{arrange_form, paragraph_roles, plan_form},
+    pitch::{Key, Mode},
+    pure_tone::ToneAndDuration,
+    text_analyzer::TextAnalyzer,
+};
+
+// 各小節を4/4拍子で1秒として作曲しているため、四分音符 = 240 BPM になる
+pub const DEFAULT_TEMPO: f32 = 240.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackType {
     Hiragana,
     Katakana,
     Kanji,
@@ -12,31 +23,53 @@ enum TrackType {
 
 pub struct Text2Track {
     pub text_analyzer: TextAnalyzer,
+    track_type: Option<TrackType>,
 }
 
 impl Text2Track {
     pub fn new(text_analyzer: TextAnalyzer) -> Self {
-        Self { text_analyzer }
+        Self {
+            text_analyzer,
+            track_type: None,
+        }
     }
 
-    pub fn generate_track(&self) -> Vec<ToneAndDuration> {
-        let text_length = self.text_analyzer.length();
-        let hiragana_ratio = self.text_analyzer.calculate_hiragana_ratio();
-        let katakana_ratio = self.text_analyzer.calculate_katakana_ratio();
-        let kanji_ratio = self.text_analyzer.calculate_kanji_ratio();
-        let alphabets_ratio = self.text_analyzer.calculate_alphabets_ratio();
+    // テキストの文字種に関係なく曲調を固定する
+    pub fn with_track_type(mut self, track_type: TrackType) -> Self {
+        self.track_type = Some(track_type);
+        self
+    }
 
-        let track = match self.determine_track_type() {
+    pub fn generate_track(&self) -> Vec<ToneAndDuration> {
+        let melody = match self.determine_track_type() {
             TrackType::Hiragana => self.generate_track_hiragana(),
             TrackType::Katakana => self.generate_track_katakana(),
             TrackType::Kanji => self.generate_track_kanji(),
             TrackType::Alphabets => self.generate_track_alphabets(),
         };
 
-        track
+        // 段落が1つだけなら構成を組まず、16小節の旋律をそのまま使う
+        let paragraphs = self.text_analyzer.paragraphs().len();
+        if paragraphs <= 1 {
+            return melody;
+        }
+        arrange_form(&melody, &plan_form(&paragraph_roles(paragraphs)))
+    }
+
+    pub fn key(&self) -> Key {
+        match self.determine_track_type() {
+            TrackType::Hiragana => Key::new(0, Mode::Major),
+            TrackType::Katakana => Key::new(7, Mode::Major),
+            TrackType::Kanji => Key::new(7, Mode::Major),
+            TrackType::Alphabets => Key::new(0, Mode::Major),
+        }
     }
 
     fn determine_track_type(&self) -> TrackType {
+        if let Some(track_type) = self.track_type {
+            return track_type;
+        }
+
         let hiragana_ratio = self.text_analyzer.calculate_hiragana_ratio();
         let katakana_ratio = self.text_analyzer.calculate_katakana_ratio();
         let kanji_ratio = self.text_analyzer.calculate_kanji_ratio();
@@ -54,7 +87,7 @@ impl Text2Track {
             .map(|(_, track_type)| track_type)
             .unwrap_or(&TrackType::Alphabets);
 
-        track_type.clone()
+        *track_type
     }
 
     fn generate_track_hiragana(&self) -> Vec<ToneAndDuration> {
@@ -797,6 +830,7 @@ impl Text2Track {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::form::Section;
     use rstest::rstest;
 
     #[test]
@@ -806,17 +840,236 @@ mod tests {
         assert_eq!(text2track.text_analyzer.text, "こんにちは".to_string());
     }
 
+    fn seconds(track: &[ToneAndDuration]) -> f32 {
+        track.iter().map(|tone| tone.duration).sum()
+    }
+
+    #[test]
+    fn test_generate_track() {
+        let cases = vec![
+            (
+                "こんにちは、私の名前はおもちです。",
+                vec![
+                    (261.63, 1.0 / 2.0),
+                    (293.66, 1.0 / 2.0),
+                    (349.23, 3.0 / 4.0),
+                    (392.00, 1.0 / 4.0),
+                    (349.23, 1.0 / 2.0),
+                    (293.66, 1.0 / 2.0),
+                    (261.63, 1.0),
+                    (392.00, 1.0 / 2.0),
+                    (349.23, 1.0 / 2.0),
+                    (293.66, 3.0 / 4.0),
+                    (261.63, 1.0 / 4.0),
+                    (293.66, 1.0 / 2.0),
+                    (349.23, 1.0 / 2.0),
+                    (392.00, 1.0),
+                    (523.25, 1.0 / 2.0),
+                    (392.00, 1.0 / 2.0),
+                    (349.23, 3.0 / 4.0),
+                    (293.66, 1.0 / 4.0),
+                    (261.63, 1.0),
+                    (293.66, 1.0),
+                    (349.23, 1.0 / 2.0),
+                    (392.00, 1.0 / 2.0),
+                    (349.23, 3.0 / 4.0),
+                    (293.66, 1.0 / 4.0),
+                    (261.63, 1.0 / 2.0),
+                    (293.66, 1.0 / 2.0),
+                    (261.63, 1.0),
+                ],
+            ),
+            (
+                "ヘイ！元気デスカ？",
+                vec![
+                    (392.00, 1.0 / 3.0),
+                    (440.00, 1.0 / 6.0),
+                    (493.88, 1.0 / 2.0),
+                    (523.25, 1.0 / 3.0),
+                    (493.88, 1.0 / 6.0),
+                    (440.00, 1.0 / 2.0),
+                    (392.00, 1.0 / 6.0),
+                    (440.00, 1.0 / 6.0),
+                    (493.88, 1.0 / 6.0),
+                    (523.25, 1.0 / 2.0),
+                    (493.88, 3.0 / 4.0),
+                    (440.00, 1.0 / 4.0),
+                    (523.25, 1.0 / 6.0),
+                    (587.33, 1.0 / 6.0),
+                    (659.26, 1.0 / 6.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 1.0 / 3.0),
+                    (493.88, 1.0 / 6.0),
+                    (440.00, 1.0 / 2.0),
+                    (392.00, 1.0 / 6.0),
+                    (440.00, 1.0 / 6.0),
+                    (493.88, 1.0 / 6.0),
+                    (523.25, 1.0 / 2.0),
+                    (493.88, 1.0),
+                    (659.26, 1.0 / 3.0),
+                    (587.33, 1.0 / 6.0),
+                    (523.25, 1.0 / 2.0),
+                    (493.88, 1.0 / 6.0),
+                    (523.25, 1.0 / 6.0),
+                    (493.88, 1.0 / 6.0),
+                    (440.00, 1.0 / 2.0),
+                    (523.25, 1.0 / 6.0),
+                    (587.33, 1.0 / 6.0),
+                    (659.26, 1.0 / 6.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 3.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (659.26, 1.0 / 6.0),
+                    (587.33, 1.0 / 6.0),
+                    (523.25, 1.0 / 6.0),
+                    (493.88, 1.0 / 2.0),
+                    (440.00, 1.0 / 3.0),
+                    (493.88, 1.0 / 6.0),
+                    (523.25, 1.0 / 2.0),
+                    (493.88, 1.0 / 6.0),
+                    (440.00, 1.0 / 6.0),
+                    (392.00, 1.0 / 6.0),
+                    (440.00, 1.0 / 2.0),
+                    (392.00, 1.0),
+                ],
+            ),
+            (
+                "東京特許許可局に行く",
+                vec![
+                    (392.00, 3.0 / 4.0),
+                    (440.00, 1.0 / 4.0),
+                    (523.25, 1.0 / 2.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 1.0 / 4.0),
+                    (440.00, 1.0 / 4.0),
+                    (392.00, 1.0 / 2.0),
+                    (329.63, 3.0 / 4.0),
+                    (392.00, 1.0 / 4.0),
+                    (523.25, 1.0 / 2.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (440.00, 1.0),
+                    (587.33, 1.0 / 4.0),
+                    (659.26, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (440.00, 3.0 / 4.0),
+                    (392.00, 1.0 / 4.0),
+                    (783.99, 1.0 / 2.0),
+                    (659.26, 1.0 / 2.0),
+                    (587.33, 3.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (659.26, 1.0 / 2.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (440.00, 1.0),
+                    (523.25, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (659.26, 1.0 / 2.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 1.0 / 2.0),
+                    (440.00, 1.0 / 2.0),
+                    (392.00, 1.0 / 2.0),
+                    (392.00, 1.0),
+                ],
+            ),
+            (
+                "Why Japanese people!?",
+                vec![
+                    (493.88, 3.0 / 8.0),
+                    (523.25, 1.0 / 8.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (466.16, 1.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (440.00, 1.0 / 2.0),
+                    (392.00, 1.0 / 4.0),
+                    (415.30, 1.0 / 8.0),
+                    (440.00, 1.0 / 8.0),
+                    (466.16, 1.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (523.25, 3.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (659.26, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (493.88, 3.0 / 8.0),
+                    (523.25, 3.0 / 8.0),
+                    (493.88, 1.0 / 4.0),
+                    (440.00, 1.0 / 4.0),
+                    (466.16, 1.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (587.33, 1.0),
+                    (698.46, 1.0 / 4.0),
+                    (659.26, 1.0 / 4.0),
+                    (622.25, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (587.33, 1.0 / 4.0),
+                    (659.26, 1.0 / 4.0),
+                    (698.46, 1.0 / 4.0),
+                    (783.99, 3.0 / 8.0),
+                    (739.99, 1.0 / 8.0),
+                    (698.46, 1.0 / 4.0),
+                    (659.26, 1.0 / 4.0),
+                    (622.25, 1.0 / 2.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 1.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (466.16, 1.0 / 4.0),
+                    (440.00, 1.0 / 4.0),
+                    (392.00, 3.0 / 8.0),
+                    (440.00, 3.0 / 8.0),
+                    (466.16, 1.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                    (523.25, 1.0 / 4.0),
+                    (587.33, 1.0 / 2.0),
+                    (523.25, 3.0 / 4.0),
+                    (493.88, 1.0 / 4.0),
+                ],
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let text2track = Text2Track::new(TextAnalyzer::new(input.to_string()));
+            let expected: Vec<ToneAndDuration> = expected
+                .into_iter()
+                .map(|(frequency, duration)| ToneAndDuration {
+                    frequency,
+                    duration,
+                })
+                .collect();
+
+            // 段落が1つなら16小節の旋律をそのまま使う
+            assert_eq!(text2track.generate_track(), expected, "{}", input);
+            assert!((seconds(&expected) - 16.0).abs() < 1e-4);
+        }
+    }
+
     #[rstest]
-    #[case::hiragana("こんにちは、私の名前はおもちです。", vec![ToneAndDuration{ frequency: 261.63, duration: 1.0 / 4.0 * 1.5 }])]
-    #[case::katakana("ヘイ！元気デスカ？", vec![ToneAndDuration{ frequency: 261.63, duration: 1.0 / 4.0 * 1.5 }])]
-    #[case::kanji("東京特許許可局に行く", vec![ToneAndDuration{ frequency: 261.63, duration: 1.0 / 4.0 * 1.5 }])]
-    #[case::alphabets("Why Japanese people!?", vec![ToneAndDuration{ frequency: 261.63, duration: 1.0 / 4.0 * 1.5 }])]
-    fn test_generate_track(#[case] input: String, #[case] expected: Vec<ToneAndDuration>) {
-        let text_analyzer = TextAnalyzer::new(input);
-        let text2track = Text2Track::new(text_analyzer);
+    #[case::two_paragraphs("はじめに。\n\nおわりに。", 18.0)]
+    #[case::three_paragraphs("はじめに。\n\nほんぶん。\n\nおわりに。", 22.0)]
+    fn test_generate_track_with_paragraphs(#[case] input: String, #[case] expected_seconds: f32) {
+        let text2track = Text2Track::new(TextAnalyzer::new(input));
+        let melody = text2track.generate_track_hiragana();
         let track = text2track.generate_track();
 
-        assert_eq!(track, expected);
+        assert!((seconds(&track) - expected_seconds).abs() < 1e-4);
+        // どのセクションも旋律から小節単位で切り出せる
+        for (section, bars) in [
+            (Section::Intro, 4.0),
+            (Section::Verse, 4.0),
+            (Section::Bridge, 4.0),
+            (Section::Chorus, 4.0),
+            (Section::Outro, 2.0),
+        ] {
+            assert!((seconds(&arrange_form(&melody, &[section])) - bars).abs() < 1e-4);
+        }
+        // イントロは旋律の冒頭、最後のコーラスとアウトロは旋律の終わりで締める
+        assert_eq!(&track[..melody.len() / 4], &melody[..melody.len() / 4]);
+        assert_eq!(track.last(), melody.last());
     }
 
     #[rstest]
@@ -831,4 +1084,25 @@ mod tests {
 
         assert_eq!(track_type, expected);
     }
+
+    #[test]
+    fn test_with_track_type() {
+        let text_analyzer = TextAnalyzer::new("こんにちは".to_string());
+        let text2track = Text2Track::new(text_analyzer).with_track_type(TrackType::Katakana);
+
+        assert_eq!(text2track.determine_track_type(), TrackType::Katakana);
+        assert_eq!(text2track.key(), Key::new(7, Mode::Major));
+    }
+
+    #[rstest]
+    #[case::hiragana("こんにちは、私の名前はおもちです。", Key::new(0, Mode::Major))]
+    #[case::katakana("ヘイ！元気デスカ？", Key::new(7, Mode::Major))]
+    #[case::kanji("東京特許許可局に行く", Key::new(7, Mode::Major))]
+    #[case::alphabets("Why Japanese people!?", Key::new(0, Mode::Major))]
+    fn test_key(#[case] input: String, #[case] expected: Key) {
+        let text_analyzer = TextAnalyzer::new(input);
+        let text2track = Text2Track::new(text_analyzer);
+
+        assert_eq!(text2track.key(), expected);
+    }
 }
@@ -1,7 +1,23 @@
+use std::ops::Range;
+
 pub struct TextAnalyzer {
     pub text: String,
 }
 
+// テキスト中の範囲（文字単位、end は含まない）と前後の空白を除いた内容
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl Segment {
+    pub fn length(&self) -> usize {
+        self.end - self.start
+    }
+}
+
 impl TextAnalyzer {
     pub fn new(text: String) -> Self {
         Self { text }
@@ -27,6 +43,82 @@ impl TextAnalyzer {
         self.count_alphabets() as f32 / self.length() as f32
     }
 
+    pub fn title(&self) -> String {
+        self.text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    pub fn count_keywords(&self, keywords: &[&str]) -> usize {
+        let text = self.text.to_lowercase();
+        keywords
+            .iter()
+            .map(|keyword| {
+                if !keyword.is_ascii() {
+                    return text.matches(keyword).count();
+                }
+                // 英単語は "steam" の中の "team" のような単語の一部を数えない
+                text.match_indices(keyword)
+                    .filter(|(i, _)| {
+                        !text[..*i]
+                            .chars()
+                            .next_back()
+                            .is_some_and(|c| c.is_ascii_alphanumeric())
+                            && !text[i + keyword.len()..]
+                                .chars()
+                                .next()
+                                .is_some_and(|c| c.is_ascii_alphanumeric())
+                    })
+                    .count()
+            })
+            .sum()
+    }
+
+    // 空行で区切られた段落
+    pub fn paragraphs(&self) -> Vec<Segment> {
+        let chars: Vec<char> = self.text.chars().collect();
+        // 次の行が空白だけなら段落の終わり
+        split(&chars, 0..chars.len(), |i| {
+            chars[i] == '\n'
+                && chars[i + 1..]
+                    .iter()
+                    .take_while(|&&c| c != '\n')
+                    .all(|c| c.is_whitespace())
+        })
+    }
+
+    // 改行でも文を区切る
+    pub fn sentences(&self) -> Vec<Segment> {
+        let chars: Vec<char> = self.text.chars().collect();
+        split(&chars, 0..chars.len(), |i| {
+            let next = chars.get(i + 1).copied();
+            chars[i] == '\n'
+                || (is_sentence_terminator(chars[i])
+                    && !next.is_some_and(is_sentence_terminator)
+                    // 半角のピリオドなどは "3.14" のように文中にも現れる
+                    && (!chars[i].is_ascii() || next.is_none_or(char::is_whitespace)))
+        })
+    }
+
+    // 文を読点で区切った節
+    pub fn clauses(&self) -> Vec<Segment> {
+        let chars: Vec<char> = self.text.chars().collect();
+        self.sentences()
+            .into_iter()
+            .flat_map(|sentence| {
+                split(&chars, sentence.start..sentence.end, |i| {
+                    is_clause_separator(chars[i])
+                        // "1,000" のような数字の区切りでは分けない
+                        && (!chars[i].is_ascii()
+                            || chars.get(i + 1).is_none_or(|c| c.is_whitespace()))
+                })
+            })
+            .collect()
+    }
+
     fn count_hiragana(&self) -> usize {
         self.text
             .chars()
@@ -56,6 +148,44 @@ impl TextAnalyzer {
     }
 }
 
+fn is_sentence_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '。' | '！' | '？')
+}
+
+fn is_clause_separator(c: char) -> bool {
+    matches!(c, ',' | '、' | '，')
+}
+
+// is_end が真になる文字までを1つの区切りとし、空白だけの区切りは除く
+fn split(chars: &[char], range: Range<usize>, is_end: impl Fn(usize) -> bool) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut start = range.start;
+    for i in range.clone() {
+        if i + 1 < range.end && !is_end(i) {
+            continue;
+        }
+        let leading = chars[start..=i]
+            .iter()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        let trailing = chars[start + leading..=i]
+            .iter()
+            .rev()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        let (segment_start, segment_end) = (start + leading, i + 1 - trailing);
+        if segment_start < segment_end {
+            segments.push(Segment {
+                start: segment_start,
+                end: segment_end,
+                text: chars[segment_start..segment_end].iter().collect(),
+            });
+        }
+        start = i + 1;
+    }
+    segments
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -138,6 +268,144 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_title() {
+        let cases = vec![
+            ("Hello, world!", "Hello, world!"),
+            ("\n  吾輩は猫である  \n名前はまだ無い。", "吾輩は猫である"),
+            ("", ""),
+        ];
+
+        for (text, expected) in cases {
+            let text2param = TextAnalyzer::new(text.to_string());
+            assert_eq!(text2param.title(), expected);
+        }
+    }
+
+    fn segments(segments: Vec<Segment>) -> Vec<(usize, usize, String)> {
+        segments
+            .into_iter()
+            .map(|segment| (segment.start, segment.end, segment.text))
+            .collect()
+    }
+
+    fn expected(segments: Vec<(usize, usize, &str)>) -> Vec<(usize, usize, String)> {
+        segments
+            .into_iter()
+            .map(|(start, end, text)| (start, end, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        let cases = vec![
+            (
+                "タイトル\n\n一段落目。\n続き。\n  \n\n二段落目。\n",
+                vec![
+                    (0, 4, "タイトル"),
+                    (6, 15, "一段落目。\n続き。"),
+                    (20, 25, "二段落目。"),
+                ],
+            ),
+            ("吾輩は猫である。", vec![(0, 8, "吾輩は猫である。")]),
+            ("\n\n", vec![]),
+        ];
+
+        for (text, expected_paragraphs) in cases {
+            let text2param = TextAnalyzer::new(text.to_string());
+            assert_eq!(
+                segments(text2param.paragraphs()),
+                expected(expected_paragraphs)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sentences() {
+        let cases = vec![
+            (
+                "吾輩は猫である。名前はまだ無い。",
+                vec![(0, 8, "吾輩は猫である。"), (8, 16, "名前はまだ無い。")],
+            ),
+            (
+                "Why Japanese people!? Pi is 3.14.",
+                vec![(0, 21, "Why Japanese people!?"), (22, 33, "Pi is 3.14.")],
+            ),
+            (
+                "タイトル\n\n  本文",
+                vec![(0, 4, "タイトル"), (8, 10, "本文")],
+            ),
+            (
+                "ヘイ！元気デスカ？",
+                vec![(0, 3, "ヘイ！"), (3, 9, "元気デスカ？")],
+            ),
+            ("", vec![]),
+        ];
+
+        for (text, expected_sentences) in cases {
+            let text2param = TextAnalyzer::new(text.to_string());
+            assert_eq!(
+                segments(text2param.sentences()),
+                expected(expected_sentences)
+            );
+        }
+    }
+
+    #[test]
+    fn test_clauses() {
+        let cases = vec![
+            (
+                "こんにちは、私の名前はおもちです。",
+                vec![(0, 6, "こんにちは、"), (6, 17, "私の名前はおもちです。")],
+            ),
+            (
+                "Hello, world! It costs 1,000 yen.",
+                vec![
+                    (0, 6, "Hello,"),
+                    (7, 13, "world!"),
+                    (14, 33, "It costs 1,000 yen."),
+                ],
+            ),
+            // 文をまたいで節をつなげない
+            (
+                "はい。いいえ、違う",
+                vec![(0, 3, "はい。"), (3, 7, "いいえ、"), (7, 9, "違う")],
+            ),
+        ];
+
+        for (text, expected_clauses) in cases {
+            let text2param = TextAnalyzer::new(text.to_string());
+            assert_eq!(segments(text2param.clauses()), expected(expected_clauses));
+        }
+    }
+
+    #[test]
+    fn test_segment_length() {
+        let text2param = TextAnalyzer::new("吾輩は猫である。名前はまだ無い。".to_string());
+        let lengths: Vec<usize> = text2param.sentences().iter().map(Segment::length).collect();
+        assert_eq!(lengths, vec![8, 8]);
+    }
+
+    #[test]
+    fn test_count_keywords() {
+        let cases = vec![
+            ("戦国時代の歴史", vec!["歴史", "時代"], 2),
+            ("History of the Roman Empire", vec!["history", "empire"], 2),
+            ("サッカーの試合、野球の試合", vec!["試合"], 2),
+            ("こんにちは, world!", vec!["歴史"], 0),
+            ("Full steam ahead", vec!["team"], 0),
+            ("Metadata in the database", vec!["data"], 0),
+            ("The goalkeeper saved it", vec!["goal"], 0),
+            ("Big data, one team. Goal!", vec!["data", "team", "goal"], 3),
+            ("Pythonでdataを扱う", vec!["data"], 1),
+        ];
+
+        for (text, keywords, expected) in cases {
+            let text2param = TextAnalyzer::new(text.to_string());
+            assert_eq!(text2param.count_keywords(&keywords), expected);
+        }
+    }
+
     #[test]
     fn test_calculate_hiragana_ratio() {
         let cases = vec![
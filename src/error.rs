@@ -0,0 +1,94 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Wav(hound::Error),
+    #[cfg(feature = "opus")]
+    Opus(audiopus::Error),
+    Stream(rodio::StreamError),
+    Play(rodio::PlayError),
+    Json(serde_json::Error),
+    InvalidConfig(&'static str),
+    DuplicateId(String),
+    InvalidSoundFont(&'static str),
+    UnsupportedFormat(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::Wav(error) => write!(f, "WAV error: {}", error),
+            #[cfg(feature = "opus")]
+            Error::Opus(error) => write!(f, "Opus error: {}", error),
+            Error::Stream(error) => write!(f, "audio output error: {}", error),
+            Error::Play(error) => write!(f, "playback error: {}", error),
+            Error::Json(error) => write!(f, "JSON error: {}", error),
+            Error::InvalidConfig(reason) => write!(f, "invalid config: {}", reason),
+            Error::DuplicateId(id) => write!(f, "duplicate input id: {}", id),
+            Error::InvalidSoundFont(reason) => write!(f, "invalid SoundFont: {}", reason),
+            Error::UnsupportedFormat(reason) => write!(f, "unsupported format: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Wav(error) => Some(error),
+            #[cfg(feature = "opus")]
+            Error::Opus(error) => Some(error),
+            Error::Stream(error) => Some(error),
+            Error::Play(error) => Some(error),
+            Error::Json(error) => Some(error),
+            Error::InvalidConfig(_)
+            | Error::DuplicateId(_)
+            | Error::InvalidSoundFont(_)
+            | Error::UnsupportedFormat(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<hound::Error> for Error {
+    fn from(error: hound::Error) -> Self {
+        match error {
+            hound::Error::IoError(error) => Error::Io(error),
+            error => Error::Wav(error),
+        }
+    }
+}
+
+impl From<rodio::StreamError> for Error {
+    fn from(error: rodio::StreamError) -> Self {
+        Error::Stream(error)
+    }
+}
+
+impl From<rodio::PlayError> for Error {
+    fn from(error: rodio::PlayError) -> Self {
+        Error::Play(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+#[cfg(feature = "opus")]
+impl From<audiopus::Error> for Error {
+    fn from(error: audiopus::Error) -> Self {
+        Error::Opus(error)
+    }
+}
@@ -1 +1,11 @@
+pub mod export_abc;
+pub mod export_flac;
+pub mod export_lilypond;
+pub mod export_midi;
+pub mod export_musicxml;
+#[cfg(feature = "opus")]
+pub mod export_opus;
 pub mod export_wav;
+pub mod import_sf2;
+pub mod import_wav;
+pub mod playback;
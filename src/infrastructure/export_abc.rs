@@ -0,0 +1,264 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use crate::{
+    domain::{
+        notation::{NotatedNote, Score, BEATS_PER_MEASURE, DIVISIONS_PER_QUARTER},
+        pitch::{Key, Mode, SpelledPitch},
+    },
+    error::Result,
+};
+
+// 単位音長を16分音符にすると、すべての音価が整数倍で書ける
+const DIVISIONS_PER_UNIT: u32 = DIVISIONS_PER_QUARTER / 4;
+
+pub fn export_abc(score: &Score, path: &Path) -> Result<()> {
+    std::fs::write(path, encode_abc(score))?;
+    Ok(())
+}
+
+pub fn encode_abc(score: &Score) -> String {
+    let mut abc = String::new();
+    writeln!(abc, "X:1").unwrap();
+    writeln!(abc, "T:{}", score.title).unwrap();
+    writeln!(abc, "M:{}/4", BEATS_PER_MEASURE).unwrap();
+    writeln!(abc, "L:1/16").unwrap();
+    writeln!(abc, "Q:1/4={}", score.tempo.round()).unwrap();
+    writeln!(abc, "K:{}", key_name(&score.key)).unwrap();
+
+    let mut lyrics = vec![];
+    for (i, measure) in score.measures.iter().enumerate() {
+        // 臨時記号は小節内でのみ有効
+        let mut accidentals = HashMap::new();
+        let mut tuplet_remaining = 0;
+        for (j, note) in measure.notes.iter().enumerate() {
+            if note.tuplet_start {
+                tuplet_remaining = measure.notes[j..]
+                    .iter()
+                    .position(|note| note.tuplet_stop)
+                    .map_or(1, |position| position + 1);
+                write!(abc, "(3:2:{}", tuplet_remaining).unwrap();
+            }
+            write_note(&mut abc, note, &score.key, &mut accidentals);
+            if note.triplet && tuplet_remaining > 0 {
+                tuplet_remaining -= 1;
+            }
+            if note.tuplet_stop || (!note.triplet && tuplet_remaining == 0) {
+                abc.push(' ');
+            }
+
+            if note.pitch.is_some() {
+                lyrics.push(match &note.lyric {
+                    Some(lyric) => escape_lyric(lyric),
+                    None if note.tie_stop => "_".to_string(),
+                    None => "*".to_string(),
+                });
+            }
+        }
+        abc.push_str(if i == score.measures.len() - 1 {
+            "|]"
+        } else {
+            "| "
+        });
+    }
+    abc.push('\n');
+
+    if score
+        .measures
+        .iter()
+        .flat_map(|measure| &measure.notes)
+        .any(|note| note.lyric.is_some())
+    {
+        writeln!(abc, "w:{}", lyrics.join(" ")).unwrap();
+    }
+    abc
+}
+
+fn write_note(
+    abc: &mut String,
+    note: &NotatedNote,
+    key: &Key,
+    accidentals: &mut HashMap<(char, i8), i8>,
+) {
+    match &note.pitch {
+        Some(pitch) => {
+            let current = accidentals
+                .get(&(pitch.step, pitch.octave))
+                .copied()
+                .unwrap_or_else(|| key.signature_alter(pitch.step));
+            if pitch.alter != current {
+                abc.push_str(match pitch.alter {
+                    -2 => "__",
+                    -1 => "_",
+                    1 => "^",
+                    2 => "^^",
+                    _ => "=",
+                });
+                accidentals.insert((pitch.step, pitch.octave), pitch.alter);
+            }
+            abc.push_str(&pitch_name(pitch));
+        }
+        None => abc.push('z'),
+    }
+
+    // 三連符は記譜上の音価（実際の長さの3/2倍）で書く
+    let written = if note.triplet {
+        note.divisions * 3 / 2
+    } else {
+        note.divisions
+    };
+    let units = written / DIVISIONS_PER_UNIT;
+    if units != 1 {
+        write!(abc, "{}", units).unwrap();
+    }
+    if note.tie_start {
+        abc.push('-');
+    }
+}
+
+fn pitch_name(pitch: &SpelledPitch) -> String {
+    // ABCでは大文字の C が中央のド（C4）、小文字の c が C5
+    match pitch.octave {
+        octave if octave <= 4 => format!("{}{}", pitch.step, ",".repeat((4 - octave) as usize)),
+        octave => format!(
+            "{}{}",
+            pitch.step.to_ascii_lowercase(),
+            "'".repeat((octave - 5) as usize)
+        ),
+    }
+}
+
+fn key_name(key: &Key) -> String {
+    let tonic = key.spell(60 + key.tonic);
+    let accidental = match tonic.alter {
+        1 => "#",
+        -1 => "b",
+        _ => "",
+    };
+    let mode = match key.mode {
+        Mode::Major => "",
+        Mode::Minor => "m",
+    };
+    format!("{}{}{}", tonic.step, accidental, mode)
+}
+
+fn escape_lyric(lyric: &str) -> String {
+    match lyric {
+        "-" => "\\-".to_string(),
+        "*" | "_" | "~" | "|" | "\\" => "*".to_string(),
+        _ => lyric.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::pure_tone::ToneAndDuration;
+
+    fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+        ToneAndDuration {
+            frequency,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_pitch_name() {
+        let cases = vec![
+            (('C', 4), "C"),
+            (('B', 3), "B,"),
+            (('G', 2), "G,,"),
+            (('C', 5), "c"),
+            (('E', 6), "e'"),
+        ];
+
+        for ((step, octave), expected) in cases {
+            let pitch = SpelledPitch {
+                step,
+                alter: 0,
+                octave,
+            };
+            assert_eq!(pitch_name(&pitch), expected);
+        }
+    }
+
+    #[test]
+    fn test_key_name() {
+        let cases = vec![
+            (Key::new(0, Mode::Major), "C"),
+            (Key::new(7, Mode::Major), "G"),
+            (Key::new(10, Mode::Major), "Bb"),
+            (Key::new(9, Mode::Minor), "Am"),
+            (Key::new(6, Mode::Minor), "F#m"),
+        ];
+
+        for (key, expected) in cases {
+            assert_eq!(key_name(&key), expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_abc() {
+        // generate_track_katakana の1小節目と2小節目の一部
+        let track = vec![
+            tone(392.00, 1.0 / 3.0),
+            tone(440.00, 1.0 / 6.0),
+            tone(493.88, 1.0 / 2.0),
+            tone(523.25, 3.0 / 4.0),
+            tone(739.99, 1.0 / 2.0),
+        ];
+        let score = Score::new(
+            "カタカナ".to_string(),
+            Key::new(0, Mode::Major),
+            240.0,
+            &track,
+            "カタカナ",
+        );
+
+        assert_eq!(
+            encode_abc(&score),
+            "X:1\n\
+             T:カタカナ\n\
+             M:4/4\n\
+             L:1/16\n\
+             Q:1/4=240\n\
+             K:C\n\
+             (3:2:2G8A4 B8 | c12 ^f4- | ^f4 z12 |]\n\
+             w:カ タ カ ナ * _\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_abc_accidentals_within_measure() {
+        let track = vec![
+            tone(466.16, 1.0 / 4.0),
+            tone(466.16, 1.0 / 4.0),
+            tone(493.88, 1.0 / 4.0),
+            tone(466.16, 1.0 / 4.0),
+        ];
+        let score = Score::new("".to_string(), Key::new(0, Mode::Major), 240.0, &track, "");
+
+        assert!(encode_abc(&score).contains("_B4 B4 =B4 _B4 |]"));
+    }
+
+    #[test]
+    fn test_encode_abc_without_zero_length() {
+        // humanize で揺れたような、分割数が半端な長さ（1〜13分割）を並べる
+        let track: Vec<ToneAndDuration> = (0..60)
+            .map(|i| {
+                let frequency = if i % 5 == 4 { 0.0 } else { 440.0 };
+                tone(frequency, (i % 13 + 1) as f32 / 48.0)
+            })
+            .collect();
+        let score = Score::new("".to_string(), Key::new(0, Mode::Major), 240.0, &track, "");
+        let abc = encode_abc(&score);
+
+        let music = abc.lines().find(|line| line.ends_with("|]")).unwrap();
+        assert!(
+            !music
+                .split(|c: char| !c.is_ascii_digit())
+                .any(|digits| digits.starts_with('0')),
+            "{}",
+            music
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use std::path::Path;
+
+use super::export_wav::{quantize, BitDepth, WavFormat};
+use crate::error::{Error, Result};
+
+const BLOCK_SIZE: usize = 4096;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_LPC_ORDER: usize = 8;
+const LPC_PRECISION: u32 = 12;
+const MAX_PARTITION_ORDER: u32 = 6;
+// 4ビットのライスパラメータのうち 15 はエスケープ用に予約されている
+const MAX_RICE_PARAMETER: u32 = 14;
+
+pub fn export_flac(format: &WavFormat, samples: &[f64], path: &Path) -> Result<()> {
+    std::fs::write(path, encode_flac(format, samples)?)?;
+    Ok(())
+}
+
+pub fn encode_flac(format: &WavFormat, samples: &[f64]) -> Result<Vec<u8>> {
+    let (bits, sample_size_code) = match format.bit_depth {
+        BitDepth::Int16 => (16, 0b100),
+        BitDepth::Int24 => (24, 0b110),
+        BitDepth::Int32 | BitDepth::Float32 => {
+            return Err(Error::UnsupportedFormat("FLAC supports 16 or 24 bits"))
+        }
+    };
+    if !(1..=8).contains(&format.channels) {
+        return Err(Error::UnsupportedFormat("FLAC supports 1 to 8 channels"));
+    }
+    // STREAMINFO のサンプルレートは20ビット
+    if !(1..1 << 20).contains(&format.sample_rate) {
+        return Err(Error::UnsupportedFormat(
+            "FLAC supports sample rates below 1048576 Hz",
+        ));
+    }
+    let channels = format.channels as usize;
+    let samples: Vec<i32> = quantize(samples.iter().copied(), bits, format.dither).collect();
+    let total_frames = samples.len() / channels;
+
+    let mut bytes = b"fLaC".to_vec();
+    bytes.extend(stream_info(format, bits, total_frames));
+    for (frame_number, block) in samples[..total_frames * channels]
+        .chunks(BLOCK_SIZE * channels)
+        .enumerate()
+    {
+        let block_size = block.len() / channels;
+        let mut writer = BitWriter::new();
+        write_frame_header(
+            &mut writer,
+            frame_number as u64,
+            block_size,
+            channels,
+            sample_size_code,
+        );
+        for channel in 0..channels {
+            let channel_samples: Vec<i64> = block
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .map(|&sample| sample as i64)
+                .collect();
+            write_subframe(&mut writer, &channel_samples, bits as u32);
+        }
+        writer.align();
+        let crc = crc16(&writer.bytes);
+        writer.write(crc as u64, 16);
+        bytes.extend(writer.bytes);
+    }
+    Ok(bytes)
+}
+
+fn stream_info(format: &WavFormat, bits: u16, total_frames: usize) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // 最後のメタデータブロックで、種類は STREAMINFO
+    writer.write(1, 1);
+    writer.write(0, 7);
+    writer.write(34, 24);
+    writer.write(BLOCK_SIZE as u64, 16);
+    writer.write(BLOCK_SIZE as u64, 16);
+    // フレームサイズは不明として 0 にする
+    writer.write(0, 24);
+    writer.write(0, 24);
+    writer.write(format.sample_rate as u64, 20);
+    writer.write(format.channels as u64 - 1, 3);
+    writer.write(bits as u64 - 1, 5);
+    writer.write(total_frames as u64, 36);
+    // MD5 は省略できる
+    writer.write(0, 64);
+    writer.write(0, 64);
+    writer.bytes
+}
+
+fn write_frame_header(
+    writer: &mut BitWriter,
+    frame_number: u64,
+    block_size: usize,
+    channels: usize,
+    sample_size_code: u64,
+) {
+    writer.write(0b11111111111110, 14);
+    writer.write(0, 1);
+    // 固定ブロックサイズ
+    writer.write(0, 1);
+    // ブロックサイズはヘッダー末尾の16ビットに、サンプルレートは STREAMINFO に従う
+    writer.write(0b0111, 4);
+    writer.write(0b0000, 4);
+    // 各チャンネルを独立に符号化する
+    writer.write(channels as u64 - 1, 4);
+    writer.write(sample_size_code, 3);
+    writer.write(0, 1);
+    for byte in utf8_number(frame_number) {
+        writer.write(byte as u64, 8);
+    }
+    writer.write(block_size as u64 - 1, 16);
+    let crc = crc8(&writer.bytes);
+    writer.write(crc as u64, 8);
+}
+
+fn write_subframe(writer: &mut BitWriter, samples: &[i64], bits: u32) {
+    if samples.iter().all(|&sample| sample == samples[0]) {
+        writer.write(0, 1);
+        writer.write(0b000000, 6);
+        writer.write(0, 1);
+        writer.write_signed(samples[0], bits);
+        return;
+    }
+
+    let best = (0..=MAX_FIXED_ORDER.min(samples.len() - 1))
+        .map(|order| fixed_subframe(samples, order))
+        .chain(lpc_subframes(samples))
+        .min_by_key(|subframe| subframe.bits);
+
+    match best {
+        Some(subframe) if subframe.bits < samples.len() as u64 * bits as u64 => {
+            write_predicted_subframe(writer, samples, bits, &subframe)
+        }
+        _ => {
+            writer.write(0, 1);
+            writer.write(0b000001, 6);
+            writer.write(0, 1);
+            for &sample in samples {
+                writer.write_signed(sample, bits);
+            }
+        }
+    }
+}
+
+enum Predictor {
+    Fixed,
+    Lpc { coefficients: Vec<i64>, shift: u32 },
+}
+
+struct Subframe {
+    order: usize,
+    predictor: Predictor,
+    residual: Vec<i64>,
+    partition_order: u32,
+    parameters: Vec<u32>,
+    // ワームアップと予測係数を除いた残差部分のビット数
+    bits: u64,
+}
+
+impl Subframe {
+    fn new(order: usize, predictor: Predictor, residual: Vec<i64>) -> Self {
+        let block_size = residual.len() + order;
+        let (partition_order, parameters, bits) = best_partitioning(&residual, block_size, order);
+        Self {
+            order,
+            predictor,
+            residual,
+            partition_order,
+            parameters,
+            bits,
+        }
+    }
+}
+
+fn fixed_subframe(samples: &[i64], order: usize) -> Subframe {
+    let residual = samples[order..]
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let i = i + order;
+            let prediction = match order {
+                0 => 0,
+                1 => samples[i - 1],
+                2 => 2 * samples[i - 1] - samples[i - 2],
+                3 => 3 * samples[i - 1] - 3 * samples[i - 2] + samples[i - 3],
+                _ => 4 * samples[i - 1] - 6 * samples[i - 2] + 4 * samples[i - 3] - samples[i - 4],
+            };
+            sample - prediction
+        })
+        .collect();
+    Subframe::new(order, Predictor::Fixed, residual)
+}
+
+fn lpc_subframes(samples: &[i64]) -> Vec<Subframe> {
+    let max_order = MAX_LPC_ORDER.min(samples.len() - 1);
+    let autocorrelation = autocorrelation(samples, max_order);
+    if autocorrelation[0] == 0.0 {
+        return vec![];
+    }
+
+    levinson_durbin(&autocorrelation, max_order)
+        .into_iter()
+        .filter_map(|coefficients| {
+            let order = coefficients.len();
+            let (coefficients, shift) = quantize_coefficients(&coefficients)?;
+            let residual: Vec<i64> = (order..samples.len())
+                .map(|i| {
+                    let prediction: i64 = coefficients
+                        .iter()
+                        .enumerate()
+                        .map(|(j, coefficient)| coefficient * samples[i - j - 1])
+                        .sum();
+                    samples[i] - (prediction >> shift)
+                })
+                .collect();
+            // 残差は32ビットに収まらなければならない
+            if residual.iter().any(|&value| i32::try_from(value).is_err()) {
+                return None;
+            }
+            let mut subframe = Subframe::new(
+                order,
+                Predictor::Lpc {
+                    coefficients,
+                    shift,
+                },
+                residual,
+            );
+            subframe.bits += (order as u64) * LPC_PRECISION as u64 + 4 + 5;
+            Some(subframe)
+        })
+        .collect()
+}
+
+fn autocorrelation(samples: &[i64], max_order: usize) -> Vec<f64> {
+    // 窓関数（ハン窓）をかけてから自己相関を求める
+    let length = samples.len();
+    let windowed: Vec<f64> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &sample)| {
+            let window =
+                0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (length - 1) as f64).cos();
+            sample as f64 * window
+        })
+        .collect();
+    (0..=max_order)
+        .map(|lag| {
+            windowed[lag..]
+                .iter()
+                .zip(&windowed)
+                .map(|(a, b)| a * b)
+                .sum()
+        })
+        .collect()
+}
+
+// 各次数の予測係数を返す。x[n] ≈ Σ a[j] * x[n - 1 - j]
+fn levinson_durbin(autocorrelation: &[f64], max_order: usize) -> Vec<Vec<f64>> {
+    let mut results = vec![];
+    let mut coefficients: Vec<f64> = vec![];
+    let mut error = autocorrelation[0];
+    for i in 0..max_order {
+        let mut accumulator = autocorrelation[i + 1];
+        for (j, coefficient) in coefficients.iter().enumerate() {
+            accumulator -= coefficient * autocorrelation[i - j];
+        }
+        let reflection = accumulator / error;
+        let previous = coefficients.clone();
+        for j in 0..i {
+            coefficients[j] = previous[j] - reflection * previous[i - 1 - j];
+        }
+        coefficients.push(reflection);
+        results.push(coefficients.clone());
+
+        error *= 1.0 - reflection * reflection;
+        if error <= 0.0 {
+            break;
+        }
+    }
+    results
+}
+
+fn quantize_coefficients(coefficients: &[f64]) -> Option<(Vec<i64>, u32)> {
+    let max = coefficients
+        .iter()
+        .fold(0.0_f64, |max, coefficient| max.max(coefficient.abs()));
+    if max <= 0.0 || !max.is_finite() {
+        return None;
+    }
+
+    // 符号を除いた LPC_PRECISION - 1 ビットに最大の係数が収まるようにシフト量を決める
+    let magnitude_bits = max.log2().floor() as i32 + 1;
+    let shift = (LPC_PRECISION as i32 - 1 - magnitude_bits).clamp(0, 15) as u32;
+    let limit = 1_i64 << (LPC_PRECISION - 1);
+    let mut error = 0.0;
+    let quantized = coefficients
+        .iter()
+        .map(|coefficient| {
+            // 丸め誤差を次の係数に持ち越す
+            let scaled = coefficient * (1 << shift) as f64 + error;
+            let rounded = scaled.round();
+            error = scaled - rounded;
+            (rounded as i64).clamp(-limit, limit - 1)
+        })
+        .collect();
+    Some((quantized, shift))
+}
+
+fn best_partitioning(residual: &[i64], block_size: usize, order: usize) -> (u32, Vec<u32>, u64) {
+    let mut best = (0, vec![], u64::MAX);
+    for partition_order in 0..=MAX_PARTITION_ORDER {
+        let partitions = 1 << partition_order;
+        if !block_size.is_multiple_of(partitions) || block_size / partitions <= order {
+            break;
+        }
+        let partition_size = block_size / partitions;
+
+        let mut parameters = vec![];
+        let mut bits = 2 + 4;
+        let mut start = 0;
+        for partition in 0..partitions {
+            let length = if partition == 0 {
+                partition_size - order
+            } else {
+                partition_size
+            };
+            let (parameter, partition_bits) = best_rice_parameter(&residual[start..start + length]);
+            parameters.push(parameter);
+            bits += 4 + partition_bits;
+            start += length;
+        }
+        if bits < best.2 {
+            best = (partition_order, parameters, bits);
+        }
+    }
+    best
+}
+
+fn best_rice_parameter(residual: &[i64]) -> (u32, u64) {
+    let folded: Vec<u64> = residual.iter().map(|&value| zigzag(value)).collect();
+    (0..=MAX_RICE_PARAMETER)
+        .map(|parameter| {
+            let bits = folded
+                .iter()
+                .map(|value| (value >> parameter) + 1 + parameter as u64)
+                .sum();
+            (parameter, bits)
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
+
+fn write_predicted_subframe(
+    writer: &mut BitWriter,
+    samples: &[i64],
+    bits: u32,
+    subframe: &Subframe,
+) {
+    writer.write(0, 1);
+    match &subframe.predictor {
+        Predictor::Fixed => writer.write(0b001000 | subframe.order as u64, 6),
+        Predictor::Lpc { .. } => writer.write(0b100000 | (subframe.order as u64 - 1), 6),
+    }
+    writer.write(0, 1);
+    for &sample in &samples[..subframe.order] {
+        writer.write_signed(sample, bits);
+    }
+    if let Predictor::Lpc {
+        coefficients,
+        shift,
+    } = &subframe.predictor
+    {
+        writer.write(LPC_PRECISION as u64 - 1, 4);
+        writer.write(*shift as u64, 5);
+        for &coefficient in coefficients {
+            writer.write_signed(coefficient, LPC_PRECISION);
+        }
+    }
+
+    // 4ビットのライスパラメータを使う符号化方式
+    writer.write(0b00, 2);
+    writer.write(subframe.partition_order as u64, 4);
+    let partition_size = samples.len() >> subframe.partition_order;
+    let mut start = 0;
+    for (partition, &parameter) in subframe.parameters.iter().enumerate() {
+        let length = if partition == 0 {
+            partition_size - subframe.order
+        } else {
+            partition_size
+        };
+        writer.write(parameter as u64, 4);
+        for &value in &subframe.residual[start..start + length] {
+            writer.write_rice(zigzag(value), parameter);
+        }
+        start += length;
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// フレーム番号は UTF-8 と同じ方式で可変長に符号化する
+fn utf8_number(value: u64) -> Vec<u8> {
+    if value < 0x80 {
+        return vec![value as u8];
+    }
+    let mut continuation = vec![];
+    let mut remaining = value;
+    // 先頭バイトに収まるビット数は、続くバイトが増えるごとに1ビット減る
+    while remaining >= 1 << (6 - continuation.len()) {
+        continuation.insert(0, 0x80 | (remaining & 0x3F) as u8);
+        remaining >>= 6;
+    }
+    let length = continuation.len() + 1;
+    let prefix = !(0xFF_u8 >> length);
+    let mut bytes = vec![prefix | remaining as u8];
+    bytes.extend(continuation);
+    bytes
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    length: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: vec![],
+            accumulator: 0,
+            length: 0,
+        }
+    }
+
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.accumulator = (self.accumulator << 1) | ((value >> i) & 1);
+            self.length += 1;
+            if self.length == 8 {
+                self.bytes.push(self.accumulator as u8);
+                self.accumulator = 0;
+                self.length = 0;
+            }
+        }
+    }
+
+    fn write_signed(&mut self, value: i64, bits: u32) {
+        self.write(value as u64 & ((1 << bits) - 1), bits);
+    }
+
+    fn write_rice(&mut self, value: u64, parameter: u32) {
+        let quotient = value >> parameter;
+        for _ in 0..quotient {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+        self.write(value, parameter);
+    }
+
+    fn align(&mut self) {
+        if self.length > 0 {
+            self.write(0, 8 - self.length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::pure_tone::{PureTones, ToneAndDuration};
+    use rstest::rstest;
+
+    fn decode(bytes: Vec<u8>) -> (claxon::metadata::StreamInfo, Vec<i32>) {
+        let mut reader = claxon::FlacReader::new(std::io::Cursor::new(bytes)).unwrap();
+        let samples = reader.samples().map(|sample| sample.unwrap()).collect();
+        (reader.streaminfo(), samples)
+    }
+
+    #[test]
+    fn test_crc() {
+        assert_eq!(crc8(b"123456789"), 0xF4);
+        assert_eq!(crc16(b"123456789"), 0xFEE8);
+    }
+
+    #[rstest]
+    #[case(0, vec![0x00])]
+    #[case(0x7F, vec![0x7F])]
+    #[case(0x80, vec![0xC2, 0x80])]
+    #[case(0x7FF, vec![0xDF, 0xBF])]
+    #[case(0x800, vec![0xE0, 0xA0, 0x80])]
+    #[case(0x10000, vec![0xF0, 0x90, 0x80, 0x80])]
+    fn test_utf8_number(#[case] value: u64, #[case] expected: Vec<u8>) {
+        assert_eq!(utf8_number(value), expected);
+    }
+
+    #[test]
+    fn test_zigzag() {
+        let cases = vec![(0, 0), (-1, 1), (1, 2), (-2, 3), (2, 4)];
+
+        for (value, expected) in cases {
+            assert_eq!(zigzag(value), expected);
+        }
+    }
+
+    #[test]
+    fn test_levinson_durbin() {
+        // x[n] = 0.9 * x[n - 1] の自己相関
+        let autocorrelation = vec![1.0, 0.9, 0.81];
+        let coefficients = levinson_durbin(&autocorrelation, 2);
+
+        assert!((coefficients[0][0] - 0.9).abs() < 1e-9);
+        assert!((coefficients[1][0] - 0.9).abs() < 1e-9);
+        assert!(coefficients[1][1].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_encode_flac_round_trip() {
+        let track = vec![
+            ToneAndDuration {
+                frequency: 261.63,
+                duration: 0.25,
+            },
+            ToneAndDuration {
+                frequency: 392.0,
+                duration: 0.25,
+            },
+        ];
+        let pure_tones = PureTones::new(44100, track);
+        let format = WavFormat::new(1, 44100, BitDepth::Int16);
+        let bytes = encode_flac(&format, &pure_tones.samples).unwrap();
+
+        let (stream_info, decoded) = decode(bytes.clone());
+        assert_eq!(stream_info.sample_rate, 44100);
+        assert_eq!(stream_info.bits_per_sample, 16);
+        assert_eq!(stream_info.samples, Some(pure_tones.samples.len() as u64));
+        let expected: Vec<i32> = quantize(pure_tones.samples.iter().copied(), 16, false).collect();
+        assert_eq!(decoded, expected);
+        // 正弦波は予測がよく効くので 16ビット WAV の半分以下になる
+        assert!(bytes.len() < pure_tones.samples.len());
+    }
+
+    #[test]
+    fn test_encode_flac_stereo_24_bit() {
+        let samples: Vec<f64> = (0..5000)
+            .map(|i| match i % 2 {
+                0 => (i as f64 * 0.01).sin() * 0.8,
+                _ => ((i * 7919 % 1000) as f64 / 500.0 - 1.0) * 0.5,
+            })
+            .collect();
+        let format = WavFormat::new(2, 48000, BitDepth::Int24);
+
+        let (stream_info, decoded) = decode(encode_flac(&format, &samples).unwrap());
+        assert_eq!(stream_info.channels, 2);
+        assert_eq!(stream_info.bits_per_sample, 24);
+        assert_eq!(
+            decoded,
+            quantize(samples.iter().copied(), 24, false).collect::<Vec<i32>>()
+        );
+    }
+
+    #[test]
+    fn test_encode_flac_silence() {
+        let format = WavFormat::new(1, 8000, BitDepth::Int16);
+        let (_, decoded) = decode(encode_flac(&format, &[0.0; 100]).unwrap());
+
+        assert_eq!(decoded, vec![0; 100]);
+    }
+
+    #[test]
+    fn test_encode_flac_unsupported_format() {
+        let cases = vec![
+            WavFormat::new(1, 8000, BitDepth::Float32),
+            WavFormat::new(9, 8000, BitDepth::Int16),
+            WavFormat::new(1, 1 << 20, BitDepth::Int16),
+            WavFormat::new(1, 0, BitDepth::Int16),
+        ];
+
+        for format in cases {
+            assert!(matches!(
+                encode_flac(&format, &[0.0]),
+                Err(Error::UnsupportedFormat(_))
+            ));
+        }
+    }
+}
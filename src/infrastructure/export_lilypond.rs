@@ -0,0 +1,231 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    domain::{
+        notation::{NotatedNote, NoteValue, Score, BEATS_PER_MEASURE},
+        pitch::{Key, Mode, SpelledPitch},
+    },
+    error::Result,
+};
+
+pub fn export_lilypond(score: &Score, path: &Path) -> Result<()> {
+    std::fs::write(path, encode_lilypond(score))?;
+    Ok(())
+}
+
+pub fn encode_lilypond(score: &Score) -> String {
+    let mut ly = String::new();
+    ly.push_str("\\version \"2.24.0\"\n\n");
+    writeln!(
+        ly,
+        "\\header {{\n  title = {}\n  tagline = ##f\n}}\n",
+        quote(&score.title)
+    )
+    .unwrap();
+
+    ly.push_str("melody = {\n");
+    ly.push_str("  \\clef treble\n");
+    writeln!(ly, "  {}", key_signature(&score.key)).unwrap();
+    writeln!(ly, "  \\time {}/4", BEATS_PER_MEASURE).unwrap();
+    writeln!(ly, "  \\tempo 4 = {}", score.tempo.round()).unwrap();
+    for measure in &score.measures {
+        let notes: Vec<String> = measure.notes.iter().map(note).collect();
+        writeln!(ly, "  {} |", notes.join(" ")).unwrap();
+    }
+    ly.push_str("  \\bar \"|.\"\n}\n");
+
+    let syllables: Vec<String> = score
+        .measures
+        .iter()
+        .flat_map(|measure| &measure.notes)
+        .filter_map(|note| note.lyric.as_deref())
+        .map(quote)
+        .collect();
+    let has_lyrics = !syllables.is_empty();
+    if has_lyrics {
+        writeln!(
+            ly,
+            "\nwords = \\lyricmode {{\n  {}\n}}",
+            syllables.join(" ")
+        )
+        .unwrap();
+    }
+
+    ly.push_str("\n\\score {\n  <<\n");
+    ly.push_str("    \\new Voice = \"melody\" \\melody\n");
+    if has_lyrics {
+        ly.push_str("    \\new Lyrics \\lyricsto \"melody\" \\words\n");
+    }
+    ly.push_str("  >>\n  \\layout { }\n  \\midi { }\n}\n");
+    ly
+}
+
+fn note(note: &NotatedNote) -> String {
+    let mut ly = String::new();
+    if note.tuplet_start {
+        ly.push_str("\\tuplet 3/2 { ");
+    }
+    match &note.pitch {
+        Some(pitch) => ly.push_str(&pitch_name(pitch)),
+        None => ly.push('r'),
+    }
+    ly.push_str(match note.value {
+        NoteValue::Whole => "1",
+        NoteValue::Half => "2",
+        NoteValue::Quarter => "4",
+        NoteValue::Eighth => "8",
+        NoteValue::Sixteenth => "16",
+    });
+    ly.push_str(&".".repeat(note.dots as usize));
+    if note.tie_start {
+        ly.push('~');
+    }
+    if note.tuplet_stop {
+        ly.push_str(" }");
+    }
+    ly
+}
+
+fn pitch_name(pitch: &SpelledPitch) -> String {
+    let accidental = match pitch.alter {
+        -2 => "eses",
+        -1 => "es",
+        1 => "is",
+        2 => "isis",
+        _ => "",
+    };
+    // c' が中央のド（C4）
+    let octave = match pitch.octave - 3 {
+        marks if marks >= 0 => "'".repeat(marks as usize),
+        marks => ",".repeat(-marks as usize),
+    };
+    let name = format!("{}{}", pitch.step.to_ascii_lowercase(), accidental);
+    // オランダ語の音名では ees, aes を es, as と綴る
+    let name = match name.as_str() {
+        "ees" => "es".to_string(),
+        "aes" => "as".to_string(),
+        "eeses" => "eses".to_string(),
+        "aeses" => "ases".to_string(),
+        _ => name,
+    };
+    format!("{}{}", name, octave)
+}
+
+fn key_signature(key: &Key) -> String {
+    let tonic = key.spell(60 + key.tonic);
+    let tonic = pitch_name(&SpelledPitch { octave: 3, ..tonic });
+    let mode = match key.mode {
+        Mode::Major => "\\major",
+        Mode::Minor => "\\minor",
+    };
+    format!("\\key {} {}", tonic, mode)
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::pure_tone::ToneAndDuration;
+
+    fn tone(frequency: f32, duration: f32) -> ToneAndDuration {
+        ToneAndDuration {
+            frequency,
+            duration,
+        }
+    }
+
+    #[test]
+    fn test_pitch_name() {
+        let cases = vec![
+            (('C', 0, 4), "c'"),
+            (('B', -1, 4), "bes'"),
+            (('E', -1, 5), "es''"),
+            (('A', -1, 4), "as'"),
+            (('F', 1, 3), "fis"),
+            (('G', 0, 2), "g,"),
+        ];
+
+        for ((step, alter, octave), expected) in cases {
+            let pitch = SpelledPitch {
+                step,
+                alter,
+                octave,
+            };
+            assert_eq!(pitch_name(&pitch), expected);
+        }
+    }
+
+    #[test]
+    fn test_key_signature() {
+        let cases = vec![
+            (Key::new(0, Mode::Major), "\\key c \\major"),
+            (Key::new(3, Mode::Major), "\\key es \\major"),
+            (Key::new(9, Mode::Minor), "\\key a \\minor"),
+        ];
+
+        for (key, expected) in cases {
+            assert_eq!(key_signature(&key), expected);
+        }
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn test_encode_lilypond() {
+        let track = vec![
+            tone(392.00, 1.0 / 3.0),
+            tone(440.00, 1.0 / 6.0),
+            tone(493.88, 1.0 / 2.0),
+            tone(523.25, 3.0 / 4.0),
+            tone(739.99, 1.0 / 2.0),
+        ];
+        let score = Score::new(
+            "カタカナ".to_string(),
+            Key::new(7, Mode::Major),
+            240.0,
+            &track,
+            "カタカナ",
+        );
+
+        assert_eq!(
+            encode_lilypond(&score),
+            r#"\version "2.24.0"
+
+\header {
+  title = "カタカナ"
+  tagline = ##f
+}
+
+melody = {
+  \clef treble
+  \key g \major
+  \time 4/4
+  \tempo 4 = 240
+  \tuplet 3/2 { g'2 a'4 } b'2 |
+  c''2. fis''4~ |
+  fis''4 r2. |
+  \bar "|."
+}
+
+words = \lyricmode {
+  "カ" "タ" "カ" "ナ"
+}
+
+\score {
+  <<
+    \new Voice = "melody" \melody
+    \new Lyrics \lyricsto "melody" \words
+  >>
+  \layout { }
+  \midi { }
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use crate::{
+    domain::{pitch::frequency_to_midi, pure_tone::ToneAndDuration},
+    error::Result,
+};
+
+const TICKS_PER_QUARTER: u16 = 480;
+const PITCH_BEND_CENTER: u16 = 8192;
+const PITCH_BEND_RANGE: f32 = 2.0;
+const DEFAULT_VELOCITY: u8 = 100;
+const DRUM_CHANNEL: u8 = 9;
+
+pub struct MidiTrack<'a> {
+    pub track: &'a [ToneAndDuration],
+    pub program: Option<u8>,
+    pub velocities: Option<&'a [f32]>,
+}
+
+pub fn export_midi(tracks: &[MidiTrack], tempo: f32, path: &Path) -> Result<()> {
+    std::fs::write(path, encode_midi(tracks, tempo))?;
+    Ok(())
+}
+
+pub fn encode_midi(tracks: &[MidiTrack], tempo: f32) -> Vec<u8> {
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend(6_u32.to_be_bytes());
+    bytes.extend(1_u16.to_be_bytes());
+    bytes.extend((tracks.len() as u16 + 1).to_be_bytes());
+    bytes.extend(TICKS_PER_QUARTER.to_be_bytes());
+
+    bytes.extend(track_chunk(&conductor_events(tempo)));
+    for (i, track) in tracks.iter().enumerate() {
+        bytes.extend(track_chunk(&note_events(track, channel(i), tempo)));
+    }
+    bytes
+}
+
+// テンポと拍子を記録するコンダクタートラック
+fn conductor_events(tempo: f32) -> Vec<(u32, Vec<u8>)> {
+    let microseconds_per_quarter = (60_000_000.0 / tempo).round() as u32;
+    vec![
+        (
+            0,
+            [
+                &[0xFF, 0x51, 0x03][..],
+                &microseconds_per_quarter.to_be_bytes()[1..],
+            ]
+            .concat(),
+        ),
+        (0, vec![0xFF, 0x58, 0x04, 0x04, 0x02, 0x18, 0x08]),
+    ]
+}
+
+fn note_events(midi_track: &MidiTrack, channel: u8, tempo: f32) -> Vec<(u32, Vec<u8>)> {
+    let ticks_per_second = tempo / 60.0 * TICKS_PER_QUARTER as f32;
+    let mut events = vec![];
+
+    if let Some(program) = midi_track.program {
+        events.push((0, vec![0xC0 | channel, program & 0x7F]));
+    }
+    // RPN 0 でピッチベンドの幅を設定する
+    events.extend([
+        (0, vec![0xB0 | channel, 101, 0]),
+        (0, vec![0xB0 | channel, 100, 0]),
+        (0, vec![0xB0 | channel, 6, PITCH_BEND_RANGE as u8]),
+        (0, vec![0xB0 | channel, 38, 0]),
+    ]);
+
+    let mut bend = PITCH_BEND_CENTER;
+    let mut elapsed = 0.0;
+    for (i, tone_and_duration) in midi_track.track.iter().enumerate() {
+        // 累積時間から位置を求めることで丸め誤差が蓄積しないようにする
+        let start = (elapsed * ticks_per_second).round() as u32;
+        elapsed += tone_and_duration.duration;
+        let end = (elapsed * ticks_per_second).round() as u32;
+        if tone_and_duration.frequency <= 0.0 || end <= start {
+            continue;
+        }
+
+        let (note, note_bend) = midi_note(tone_and_duration.frequency);
+        let velocity = midi_track
+            .velocities
+            .and_then(|velocities| velocities.get(i))
+            .map(|velocity| (velocity * 127.0).round().clamp(1.0, 127.0) as u8)
+            .unwrap_or(DEFAULT_VELOCITY);
+
+        if note_bend != bend {
+            bend = note_bend;
+            events.push((
+                start,
+                vec![0xE0 | channel, (bend & 0x7F) as u8, (bend >> 7) as u8],
+            ));
+        }
+        events.push((start, vec![0x90 | channel, note, velocity]));
+        events.push((end, vec![0x80 | channel, note, 0]));
+    }
+
+    // 同じ時刻ではノートオフをノートオンより先に送る
+    events.sort_by_key(|(tick, event)| (*tick, event[0] & 0xF0 != 0x80));
+    events
+}
+
+fn midi_note(frequency: f32) -> (u8, u16) {
+    let note = frequency_to_midi(frequency).clamp(0.0, 127.0);
+    let nearest = note.round();
+    let bend = PITCH_BEND_CENTER as f32 + (note - nearest) / PITCH_BEND_RANGE * 8192.0;
+    (nearest as u8, bend.round().clamp(0.0, 16383.0) as u16)
+}
+
+fn channel(index: usize) -> u8 {
+    // チャンネル10はドラム専用なので飛ばす
+    let channel = (index % 15) as u8;
+    if channel >= DRUM_CHANNEL {
+        channel + 1
+    } else {
+        channel
+    }
+}
+
+fn track_chunk(events: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![];
+    let mut previous = 0;
+    for (tick, event) in events {
+        data.extend(variable_length_quantity(tick - previous));
+        data.extend(event);
+        previous = *tick;
+    }
+    data.extend([0x00, 0xFF, 0x2F, 0x00]);
+
+    let mut bytes = b"MTrk".to_vec();
+    bytes.extend((data.len() as u32).to_be_bytes());
+    bytes.extend(data);
+    bytes
+}
+
+fn variable_length_quantity(mut value: u32) -> Vec<u8> {
+    let mut bytes = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, vec![0x00])]
+    #[case(0x40, vec![0x40])]
+    #[case(0x7F, vec![0x7F])]
+    #[case(0x80, vec![0x81, 0x00])]
+    #[case(0x2000, vec![0xC0, 0x00])]
+    #[case(0x0FFFFFFF, vec![0xFF, 0xFF, 0xFF, 0x7F])]
+    fn test_variable_length_quantity(#[case] value: u32, #[case] expected: Vec<u8>) {
+        assert_eq!(variable_length_quantity(value), expected);
+    }
+
+    #[rstest]
+    #[case::a4(440.00, (69, 8192))]
+    #[case::c4(261.63, (60, 8192))]
+    #[case::quarter_semitone(446.40, (69, 9216))]
+    fn test_midi_note(#[case] frequency: f32, #[case] expected: (u8, u16)) {
+        let (note, bend) = midi_note(frequency);
+        assert_eq!(note, expected.0);
+        assert!((bend as i32 - expected.1 as i32).abs() <= 2);
+    }
+
+    #[test]
+    fn test_channel() {
+        let cases = vec![(0, 0), (8, 8), (9, 10), (14, 15), (15, 0)];
+
+        for (index, expected) in cases {
+            assert_eq!(channel(index), expected);
+        }
+    }
+
+    #[test]
+    fn test_note_events() {
+        let track = vec![
+            ToneAndDuration {
+                frequency: 261.63,
+                duration: 1.0 / 4.0,
+            },
+            ToneAndDuration {
+                frequency: 293.66,
+                duration: 1.0 / 2.0,
+            },
+        ];
+        let midi_track = MidiTrack {
+            track: &track,
+            program: Some(48),
+            velocities: Some(&[1.0, 0.5]),
+        };
+        let events = note_events(&midi_track, 0, 240.0);
+
+        let notes: Vec<(u32, Vec<u8>)> = events
+            .into_iter()
+            .filter(|(_, event)| matches!(event[0] & 0xF0, 0x80 | 0x90 | 0xC0))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![
+                (0, vec![0xC0, 48]),
+                (0, vec![0x90, 60, 127]),
+                (480, vec![0x80, 60, 0]),
+                (480, vec![0x90, 62, 64]),
+                (1440, vec![0x80, 62, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_midi() {
+        let track = vec![ToneAndDuration {
+            frequency: 440.0,
+            duration: 1.0,
+        }];
+        let bytes = encode_midi(
+            &[MidiTrack {
+                track: &track,
+                program: None,
+                velocities: None,
+            }],
+            120.0,
+        );
+
+        assert_eq!(&bytes[..8], b"MThd\x00\x00\x00\x06");
+        assert_eq!(&bytes[8..14], &[0, 1, 0, 2, 0x01, 0xE0]);
+        assert_eq!(&bytes[14..18], b"MTrk");
+        // 120 BPM = 500000 マイクロ秒/四分音符
+        assert_eq!(&bytes[22..29], &[0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]);
+        assert!(bytes.ends_with(&[0x00, 0xFF, 0x2F, 0x00]));
+    }
+}
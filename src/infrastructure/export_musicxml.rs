@@ -0,0 +1,235 @@
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    domain::{
+        notation::{NotatedNote, NoteValue, Score, BEATS_PER_MEASURE, DIVISIONS_PER_QUARTER},
+        pitch::Mode,
+    },
+    error::Result,
+};
+
+pub fn export_musicxml(score: &Score, path: &Path) -> Result<()> {
+    std::fs::write(path, encode_musicxml(score))?;
+    Ok(())
+}
+
+pub fn encode_musicxml(score: &Score) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    xml.push_str(
+        "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \
+         \"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+    );
+    xml.push_str("<score-partwise version=\"4.0\">\n");
+    writeln!(
+        xml,
+        "  <work>\n    <work-title>{}</work-title>\n  </work>",
+        escape(&score.title)
+    )
+    .unwrap();
+    xml.push_str("  <identification>\n    <encoding>\n");
+    xml.push_str("      <software>data2sound</software>\n");
+    xml.push_str("    </encoding>\n  </identification>\n");
+    xml.push_str("  <part-list>\n    <score-part id=\"P1\">\n");
+    xml.push_str("      <part-name>Melody</part-name>\n");
+    xml.push_str("    </score-part>\n  </part-list>\n");
+    xml.push_str("  <part id=\"P1\">\n");
+
+    for (i, measure) in score.measures.iter().enumerate() {
+        writeln!(xml, "    <measure number=\"{}\">", i + 1).unwrap();
+        if i == 0 {
+            write_attributes(&mut xml, score);
+        }
+        for note in &measure.notes {
+            write_note(&mut xml, note);
+        }
+        if i == score.measures.len() - 1 {
+            xml.push_str("      <barline location=\"right\">\n");
+            xml.push_str("        <bar-style>light-heavy</bar-style>\n");
+            xml.push_str("      </barline>\n");
+        }
+        xml.push_str("    </measure>\n");
+    }
+
+    xml.push_str("  </part>\n</score-partwise>\n");
+    xml
+}
+
+fn write_attributes(xml: &mut String, score: &Score) {
+    let mode = match score.key.mode {
+        Mode::Major => "major",
+        Mode::Minor => "minor",
+    };
+    xml.push_str("      <attributes>\n");
+    writeln!(
+        xml,
+        "        <divisions>{}</divisions>",
+        DIVISIONS_PER_QUARTER
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "        <key>\n          <fifths>{}</fifths>\n          <mode>{}</mode>\n        </key>",
+        score.key.fifths(),
+        mode
+    )
+    .unwrap();
+    writeln!(
+        xml,
+        "        <time>\n          <beats>{}</beats>\n          <beat-type>4</beat-type>\n        </time>",
+        BEATS_PER_MEASURE
+    )
+    .unwrap();
+    xml.push_str(
+        "        <clef>\n          <sign>G</sign>\n          <line>2</line>\n        </clef>\n",
+    );
+    xml.push_str("      </attributes>\n");
+
+    xml.push_str("      <direction placement=\"above\">\n        <direction-type>\n");
+    xml.push_str("          <metronome>\n            <beat-unit>quarter</beat-unit>\n");
+    writeln!(
+        xml,
+        "            <per-minute>{}</per-minute>",
+        score.tempo.round()
+    )
+    .unwrap();
+    xml.push_str("          </metronome>\n        </direction-type>\n");
+    writeln!(xml, "        <sound tempo=\"{}\"/>", score.tempo.round()).unwrap();
+    xml.push_str("      </direction>\n");
+}
+
+fn write_note(xml: &mut String, note: &NotatedNote) {
+    xml.push_str("      <note>\n");
+    match &note.pitch {
+        Some(pitch) => {
+            xml.push_str("        <pitch>\n");
+            writeln!(xml, "          <step>{}</step>", pitch.step).unwrap();
+            if pitch.alter != 0 {
+                writeln!(xml, "          <alter>{}</alter>", pitch.alter).unwrap();
+            }
+            writeln!(xml, "          <octave>{}</octave>", pitch.octave).unwrap();
+            xml.push_str("        </pitch>\n");
+        }
+        None => xml.push_str("        <rest/>\n"),
+    }
+    writeln!(xml, "        <duration>{}</duration>", note.divisions).unwrap();
+    if note.tie_stop {
+        xml.push_str("        <tie type=\"stop\"/>\n");
+    }
+    if note.tie_start {
+        xml.push_str("        <tie type=\"start\"/>\n");
+    }
+    xml.push_str("        <voice>1</voice>\n");
+    writeln!(xml, "        <type>{}</type>", note_type(note.value)).unwrap();
+    for _ in 0..note.dots {
+        xml.push_str("        <dot/>\n");
+    }
+    if note.triplet {
+        xml.push_str("        <time-modification>\n");
+        xml.push_str("          <actual-notes>3</actual-notes>\n");
+        xml.push_str("          <normal-notes>2</normal-notes>\n");
+        xml.push_str("        </time-modification>\n");
+    }
+
+    let notations: Vec<&str> = [
+        (note.tie_stop, "<tied type=\"stop\"/>"),
+        (note.tie_start, "<tied type=\"start\"/>"),
+        (
+            note.tuplet_start,
+            "<tuplet type=\"start\" bracket=\"yes\"/>",
+        ),
+        (note.tuplet_stop, "<tuplet type=\"stop\"/>"),
+    ]
+    .into_iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, element)| element)
+    .collect();
+    if !notations.is_empty() {
+        xml.push_str("        <notations>\n");
+        for element in notations {
+            writeln!(xml, "          {}", element).unwrap();
+        }
+        xml.push_str("        </notations>\n");
+    }
+
+    if let Some(lyric) = &note.lyric {
+        xml.push_str("        <lyric number=\"1\">\n");
+        xml.push_str("          <syllabic>single</syllabic>\n");
+        writeln!(xml, "          <text>{}</text>", escape(lyric)).unwrap();
+        xml.push_str("        </lyric>\n");
+    }
+    xml.push_str("      </note>\n");
+}
+
+fn note_type(value: NoteValue) -> &'static str {
+    match value {
+        NoteValue::Whole => "whole",
+        NoteValue::Half => "half",
+        NoteValue::Quarter => "quarter",
+        NoteValue::Eighth => "eighth",
+        NoteValue::Sixteenth => "16th",
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        pitch::{Key, Mode},
+        pure_tone::ToneAndDuration,
+    };
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("Tom & Jerry <\"'>"),
+            "Tom &amp; Jerry &lt;&quot;&apos;&gt;"
+        );
+    }
+
+    #[test]
+    fn test_encode_musicxml() {
+        let track = vec![
+            ToneAndDuration {
+                frequency: 466.16,
+                duration: 1.0 / 3.0,
+            },
+            ToneAndDuration {
+                frequency: 440.00,
+                duration: 1.0 / 6.0,
+            },
+            ToneAndDuration {
+                frequency: 392.00,
+                duration: 1.0 / 2.0,
+            },
+        ];
+        let score = Score::new(
+            "Why & How".to_string(),
+            Key::new(0, Mode::Major),
+            240.0,
+            &track,
+            "Why & How",
+        );
+        let xml = encode_musicxml(&score);
+
+        assert!(xml.contains("<work-title>Why &amp; How</work-title>"));
+        assert!(xml.contains("<fifths>0</fifths>"));
+        assert!(xml.contains("<per-minute>240</per-minute>"));
+        assert!(xml
+            .contains("<step>B</step>\n          <alter>-1</alter>\n          <octave>4</octave>"));
+        assert!(xml.contains("<actual-notes>3</actual-notes>"));
+        assert!(xml.contains("<tuplet type=\"start\" bracket=\"yes\"/>"));
+        assert!(xml.contains("<text>Why</text>"));
+        assert!(xml.contains("<text>How</text>"));
+        assert_eq!(xml.matches("<measure ").count(), 1);
+        assert_eq!(xml.matches("<note>").count(), 3);
+    }
+}
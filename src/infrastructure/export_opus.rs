@@ -0,0 +1,227 @@
+use std::path::Path;
+
+use audiopus::{coder::Encoder, Application, Bitrate, Channels, SampleRate};
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+use crate::error::{Error, Result};
+
+pub const DEFAULT_BITRATE: u32 = 96_000;
+// Opus は常に 48kHz で符号化し、グラニュール位置もこの単位で数える
+const OPUS_SAMPLE_RATE: u32 = 48000;
+// 20ms のフレーム
+const FRAME_SIZE: usize = 960;
+const MAX_PACKET_SIZE: usize = 4000;
+const SERIAL: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpusFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bitrate: u32,
+}
+
+impl OpusFormat {
+    pub fn new(channels: u16, sample_rate: u32) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            bitrate: DEFAULT_BITRATE,
+        }
+    }
+}
+
+pub fn export_opus(format: &OpusFormat, samples: &[f64], path: &Path) -> Result<()> {
+    std::fs::write(path, encode_opus(format, samples)?)?;
+    Ok(())
+}
+
+pub fn encode_opus(format: &OpusFormat, samples: &[f64]) -> Result<Vec<u8>> {
+    let channels = match format.channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        _ => return Err(Error::UnsupportedFormat("Opus supports 1 or 2 channels")),
+    };
+    let mut encoder = Encoder::new(SampleRate::Hz48000, channels, Application::Audio)?;
+    encoder.set_bitrate(Bitrate::BitsPerSecond(format.bitrate as i32))?;
+    let pre_skip = encoder.lookahead()? as u16;
+
+    let channel_count = format.channels as usize;
+    let samples = resample(samples, channel_count, format.sample_rate, OPUS_SAMPLE_RATE);
+    let total_frames = samples.len() / channel_count;
+
+    let mut writer = PacketWriter::new(vec![]);
+    writer.write_packet(
+        opus_head(format, pre_skip).into_boxed_slice(),
+        SERIAL,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    writer.write_packet(
+        opus_tags().into_boxed_slice(),
+        SERIAL,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+
+    // エンコーダは pre_skip 分だけ遅れて出力するので、その分も無音で押し出す
+    let packet_count = (total_frames + pre_skip as usize).div_ceil(FRAME_SIZE);
+    let mut packet = vec![0; MAX_PACKET_SIZE];
+    for i in 0..packet_count {
+        let start = (i * FRAME_SIZE * channel_count).min(samples.len());
+        let end = ((i + 1) * FRAME_SIZE * channel_count).min(samples.len());
+        let mut frame = samples[start..end].to_vec();
+        frame.resize(FRAME_SIZE * channel_count, 0.0);
+        let length = encoder.encode_float(&frame, &mut packet)?;
+
+        // グラニュール位置は pre_skip を含めて数え、最後のページで末尾の無音の詰め物を切り捨てる
+        let granule_position = ((i + 1) * FRAME_SIZE).min(pre_skip as usize + total_frames) as u64;
+        let end_info = if i == packet_count - 1 {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer.write_packet(
+            packet[..length].to_vec().into_boxed_slice(),
+            SERIAL,
+            end_info,
+            granule_position,
+        )?;
+    }
+    Ok(writer.into_inner())
+}
+
+fn opus_head(format: &OpusFormat, pre_skip: u16) -> Vec<u8> {
+    let mut bytes = b"OpusHead".to_vec();
+    bytes.push(1);
+    bytes.push(format.channels as u8);
+    bytes.extend(pre_skip.to_le_bytes());
+    // 元のサンプルレートは再生には使われず、情報として残すだけ
+    bytes.extend(format.sample_rate.to_le_bytes());
+    bytes.extend(0_i16.to_le_bytes());
+    // モノラルとステレオだけなのでチャンネルマッピングは 0
+    bytes.push(0);
+    bytes
+}
+
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"data2sound";
+    let mut bytes = b"OpusTags".to_vec();
+    bytes.extend((vendor.len() as u32).to_le_bytes());
+    bytes.extend(vendor);
+    bytes.extend(0_u32.to_le_bytes());
+    bytes
+}
+
+// チャンネルごとに線形補間でサンプルレートを変換する
+fn resample(samples: &[f64], channels: usize, from: u32, to: u32) -> Vec<f32> {
+    if from == to {
+        return samples.iter().map(|&sample| sample as f32).collect();
+    }
+    let source_frames = samples.len() / channels;
+    let frames = (source_frames as u64 * to as u64 / from as u64) as usize;
+    let mut resampled = Vec::with_capacity(frames * channels);
+    for i in 0..frames {
+        let position = i as f64 * from as f64 / to as f64;
+        let index = position as usize;
+        let fraction = position - index as f64;
+        for channel in 0..channels {
+            let current = samples[index * channels + channel];
+            let next = samples
+                .get((index + 1) * channels + channel)
+                .copied()
+                .unwrap_or(current);
+            resampled.push((current + (next - current) * fraction) as f32);
+        }
+    }
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiopus::{coder::Decoder, packet::Packet, MutSignals};
+
+    #[test]
+    fn test_resample() {
+        let cases = vec![
+            ((vec![0.0, 1.0], 1, 1, 1), vec![0.0, 1.0]),
+            ((vec![0.0, 1.0], 1, 1, 2), vec![0.0, 0.5, 1.0, 1.0]),
+            (
+                (vec![0.0, 0.0, 1.0, -1.0], 2, 1, 2),
+                vec![0.0, 0.0, 0.5, -0.5, 1.0, -1.0, 1.0, -1.0],
+            ),
+            ((vec![0.0, 0.5, 1.0, 0.5], 1, 2, 1), vec![0.0, 1.0]),
+        ];
+
+        for ((samples, channels, from, to), expected) in cases {
+            assert_eq!(resample(&samples, channels, from, to), expected);
+        }
+    }
+
+    #[test]
+    fn test_opus_head() {
+        let head = opus_head(&OpusFormat::new(2, 44100), 312);
+
+        assert_eq!(
+            head,
+            b"OpusHead\x01\x02\x38\x01\x44\xAC\x00\x00\x00\x00\x00".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encode_opus() {
+        let samples: Vec<f64> = (0..44100)
+            .map(|i| (i as f64 * 440.0 * 2.0 * std::f64::consts::PI / 44100.0).sin() * 0.5)
+            .collect();
+        let format = OpusFormat {
+            bitrate: 32_000,
+            ..OpusFormat::new(1, 44100)
+        };
+        let bytes = encode_opus(&format, &samples).unwrap();
+
+        let mut reader = ogg::reading::PacketReader::new(std::io::Cursor::new(bytes.clone()));
+        let head = reader.read_packet_expected().unwrap();
+        assert_eq!(&head.data[..8], b"OpusHead");
+        let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+        let tags = reader.read_packet_expected().unwrap();
+        assert_eq!(&tags.data[..8], b"OpusTags");
+
+        let mut decoder = Decoder::new(SampleRate::Hz48000, Channels::Mono).unwrap();
+        let mut decoded = vec![];
+        let mut last = tags;
+        let mut packets = 0;
+        while let Some(packet) = reader.read_packet().unwrap() {
+            let mut output = vec![0.0; FRAME_SIZE];
+            let frames = decoder
+                .decode_float(
+                    Some(Packet::try_from(&packet.data).unwrap()),
+                    MutSignals::try_from(&mut output).unwrap(),
+                    false,
+                )
+                .unwrap();
+            decoded.extend_from_slice(&output[..frames]);
+            packets += 1;
+            last = packet;
+        }
+        // 48000 フレームと先読みの 312 フレームを押し出すのに 51 パケット必要
+        assert_eq!(packets, 51);
+        assert!(last.last_in_stream());
+        assert_eq!(last.absgp_page(), pre_skip + 48000);
+
+        // pre_skip を除いた範囲に入力の全フレームが末尾まで残っている
+        let decoded = &decoded[pre_skip as usize..last.absgp_page() as usize];
+        assert_eq!(decoded.len(), 48000);
+        let tail = &decoded[decoded.len() - 480..];
+        let rms = (tail.iter().map(|sample| sample * sample).sum::<f32>() / 480.0).sqrt();
+        assert!(rms > 0.25, "{}", rms);
+        // 32kbps なので 1 秒で 4KB 程度に収まる
+        assert!(bytes.len() < 6000);
+    }
+
+    #[test]
+    fn test_encode_opus_unsupported_channels() {
+        let result = encode_opus(&OpusFormat::new(3, 48000), &[0.0; 3]);
+
+        assert!(matches!(result, Err(Error::UnsupportedFormat(_))));
+    }
+}
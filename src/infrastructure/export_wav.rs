@@ -1,21 +1,464 @@
-use std::path::Path;
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, Seek, SeekFrom, Write},
+    path::Path,
+};
 
-pub fn export_wav(
-    channels: u16,
-    sample_rate: u32,
-    bits_per_sample: u16,
-    samples: Vec<i16>,
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{domain::cue::Cue, error::Result};
+
+const SOFTWARE: &str = "data2sound";
+
+// ディザは毎回同じ出力になるよう固定のシードで生成する
+const DITHER_SEED: u64 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitDepth {
+    Int16,
+    Int24,
+    Int32,
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            BitDepth::Int16 => 16,
+            BitDepth::Int24 => 24,
+            BitDepth::Int32 | BitDepth::Float32 => 32,
+        }
+    }
+
+    fn sample_format(&self) -> hound::SampleFormat {
+        match self {
+            BitDepth::Float32 => hound::SampleFormat::Float,
+            _ => hound::SampleFormat::Int,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WavFormat {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bit_depth: BitDepth,
+    pub dither: bool,
+}
+
+impl WavFormat {
+    pub fn new(channels: u16, sample_rate: u32, bit_depth: BitDepth) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            bit_depth,
+            dither: false,
+        }
+    }
+
+    fn spec(&self) -> hound::WavSpec {
+        hound::WavSpec {
+            channels: self.channels,
+            sample_rate: self.sample_rate,
+            bits_per_sample: self.bit_depth.bits_per_sample(),
+            sample_format: self.bit_depth.sample_format(),
+        }
+    }
+}
+
+pub fn export_wav(format: &WavFormat, samples: &[f64], path: &Path) -> Result<()> {
+    export_wav_streaming(format, samples.iter().copied(), path)
+}
+
+// Renderer などから受け取ったサンプルを、全体をメモリに載せずにそのまま書き出す
+pub fn export_wav_streaming(
+    format: &WavFormat,
+    samples: impl IntoIterator<Item = f64>,
+    path: &Path,
+) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    write_wav(format, samples, file)
+}
+
+pub fn encode_wav(format: &WavFormat, samples: &[f64]) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(vec![]);
+    write_wav(format, samples.iter().copied(), &mut cursor)?;
+    Ok(cursor.into_inner())
+}
+
+pub fn write_wav<W: Write + Seek>(
+    format: &WavFormat,
+    samples: impl IntoIterator<Item = f64>,
+    writer: W,
+) -> Result<()> {
+    let mut writer = hound::WavWriter::new(writer, format.spec())?;
+    match format.bit_depth {
+        BitDepth::Float32 => {
+            for sample in samples {
+                writer.write_sample(sample as f32)?;
+            }
+        }
+        BitDepth::Int16 => {
+            for sample in quantize(samples, 16, format.dither) {
+                writer.write_sample(sample as i16)?;
+            }
+        }
+        BitDepth::Int24 | BitDepth::Int32 => {
+            let bits = format.bit_depth.bits_per_sample();
+            for sample in quantize(samples, bits, format.dither) {
+                writer.write_sample(sample)?;
+            }
+        }
+    }
+    // drop に任せるとヘッダーの書き込みエラーが握りつぶされる
+    writer.finalize()?;
+    Ok(())
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WavMetadata {
+    pub title: String,
+    pub comment: String,
+    pub cues: Vec<Cue>,
+}
+
+pub fn export_wav_with_metadata(
+    format: &WavFormat,
+    samples: &[f64],
+    metadata: &WavMetadata,
     path: &Path,
-) {
-    let spec = hound::WavSpec {
-        channels,
-        sample_rate,
-        bits_per_sample,
-        sample_format: hound::SampleFormat::Int,
-    };
-    let path = Path::new(path);
-    let mut writer = hound::WavWriter::create(path, spec).unwrap();
-    for sample in samples {
-        writer.write_sample(sample).unwrap();
+) -> Result<()> {
+    export_wav_streaming_with_metadata(format, samples.iter().copied(), metadata, path)
+}
+
+pub fn export_wav_streaming_with_metadata(
+    format: &WavFormat,
+    samples: impl IntoIterator<Item = f64>,
+    metadata: &WavMetadata,
+    path: &Path,
+) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    write_wav_with_metadata(format, samples, metadata, file)
+}
+
+pub fn encode_wav_with_metadata(
+    format: &WavFormat,
+    samples: &[f64],
+    metadata: &WavMetadata,
+) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(vec![]);
+    write_wav_with_metadata(format, samples.iter().copied(), metadata, &mut cursor)?;
+    Ok(cursor.into_inner())
+}
+
+// サンプルを書き終えてから data チャンクの後ろにメタデータのチャンクを足し、RIFF の長さを直す
+pub fn write_wav_with_metadata<W: Write + Seek>(
+    format: &WavFormat,
+    samples: impl IntoIterator<Item = f64>,
+    metadata: &WavMetadata,
+    mut writer: W,
+) -> Result<()> {
+    write_wav(format, samples, &mut writer)?;
+    let data_end = writer.seek(SeekFrom::End(0))?;
+
+    let mut chunks = vec![];
+    // hound は data チャンクの末尾をパディングしないので、後ろにチャンクを足す前に揃える
+    if data_end % 2 == 1 {
+        chunks.push(0);
+    }
+    chunks.extend(info_chunk(metadata));
+    if !metadata.cues.is_empty() {
+        chunks.extend(cue_chunk(&metadata.cues));
+        chunks.extend(label_chunk(&metadata.cues));
+    }
+    writer.write_all(&chunks)?;
+
+    let riff_size = (data_end + chunks.len() as u64 - 8) as u32;
+    writer.seek(SeekFrom::Start(4))?;
+    writer.write_all(&riff_size.to_le_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn info_chunk(metadata: &WavMetadata) -> Vec<u8> {
+    let mut data = b"INFO".to_vec();
+    for (id, text) in [
+        (b"INAM", metadata.title.as_str()),
+        (b"ISFT", SOFTWARE),
+        (b"ICMT", metadata.comment.as_str()),
+    ] {
+        if !text.is_empty() {
+            data.extend(chunk(id, &null_terminated(text)));
+        }
+    }
+    chunk(b"LIST", &data)
+}
+
+fn cue_chunk(cues: &[Cue]) -> Vec<u8> {
+    let mut data = (cues.len() as u32).to_le_bytes().to_vec();
+    for (i, cue) in cues.iter().enumerate() {
+        data.extend((i as u32 + 1).to_le_bytes());
+        data.extend(cue.frame.to_le_bytes());
+        data.extend(b"data");
+        data.extend(0_u32.to_le_bytes());
+        data.extend(0_u32.to_le_bytes());
+        data.extend(cue.frame.to_le_bytes());
+    }
+    chunk(b"cue ", &data)
+}
+
+fn label_chunk(cues: &[Cue]) -> Vec<u8> {
+    let mut data = b"adtl".to_vec();
+    for (i, cue) in cues.iter().enumerate() {
+        let mut label = (i as u32 + 1).to_le_bytes().to_vec();
+        label.extend(null_terminated(&cue.label));
+        data.extend(chunk(b"labl", &label));
+    }
+    chunk(b"LIST", &data)
+}
+
+fn chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut bytes = id.to_vec();
+    bytes.extend((data.len() as u32).to_le_bytes());
+    bytes.extend(data);
+    // チャンクは偶数バイトに揃える
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    bytes
+}
+
+fn null_terminated(text: &str) -> Vec<u8> {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.push(0);
+    bytes
+}
+
+// -1.0..=1.0 の値を指定したビット数の整数に変換する
+pub(crate) fn quantize(
+    samples: impl IntoIterator<Item = f64>,
+    bits: u16,
+    dither: bool,
+) -> impl Iterator<Item = i32> {
+    let max = ((1_i64 << (bits - 1)) - 1) as f64;
+    let mut rng = ChaCha8Rng::seed_from_u64(DITHER_SEED);
+    samples.into_iter().map(move |sample| {
+        // 三角分布（TPDF）のディザを±1LSBの範囲で加える
+        let noise = if dither {
+            rng.gen::<f64>() - rng.gen::<f64>()
+        } else {
+            0.0
+        };
+        (sample * max + noise).round().clamp(-max - 1.0, max) as i32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    use crate::domain::{instrument::Sine, pure_tone::ToneAndDuration, renderer::Renderer};
+    use rstest::rstest;
+
+    #[test]
+    fn test_export_wav() {
+        let path = std::env::temp_dir().join("data2sound_test_export_wav.wav");
+        let format = WavFormat::new(1, 8000, BitDepth::Int16);
+        export_wav(&format, &[0.0, 1.0, -1.0, 0.0], &path).unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let samples: Vec<i16> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(samples, vec![0, i16::MAX, -i16::MAX, 0]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_wav_streaming() {
+        let path = std::env::temp_dir().join("data2sound_test_export_wav_streaming.wav");
+        let format = WavFormat::new(1, 8000, BitDepth::Int16);
+        let track = vec![ToneAndDuration {
+            frequency: 440.0,
+            duration: 2.0,
+        }];
+        export_wav_streaming(&format, Renderer::new(8000, track, Sine), &path).unwrap();
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 16000);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_wav_streaming_with_metadata() {
+        let path =
+            std::env::temp_dir().join("data2sound_test_export_wav_streaming_with_metadata.wav");
+        let format = WavFormat::new(1, 8000, BitDepth::Int16);
+        let track = vec![ToneAndDuration {
+            frequency: 440.0,
+            duration: 0.5,
+        }];
+        let metadata = WavMetadata {
+            title: "A".to_string(),
+            comment: "".to_string(),
+            cues: vec![Cue {
+                frame: 0,
+                label: "A".to_string(),
+            }],
+        };
+        let samples: Vec<f64> = Renderer::new(8000, track.clone(), Sine).collect();
+        export_wav_streaming_with_metadata(
+            &format,
+            Renderer::new(8000, track, Sine),
+            &metadata,
+            &path,
+        )
+        .unwrap();
+
+        // 全体をメモリに載せて書き出した場合と同じになる
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            encode_wav_with_metadata(&format, &samples, &metadata).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encode_wav() {
+        let format = WavFormat::new(1, 8000, BitDepth::Int16);
+        let bytes = encode_wav(&format, &[0.0, 0.5, -0.5]).unwrap();
+
+        assert_eq!(&bytes[..4], b"RIFF");
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8000);
+        let samples: Vec<i16> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(samples, vec![0, 16384, -16384]);
+    }
+
+    #[rstest]
+    #[case::int16(BitDepth::Int16, hound::SampleFormat::Int, 32767)]
+    #[case::int24(BitDepth::Int24, hound::SampleFormat::Int, 8388607)]
+    #[case::int32(BitDepth::Int32, hound::SampleFormat::Int, 2147483647)]
+    fn test_encode_wav_integer_scaling(
+        #[case] bit_depth: BitDepth,
+        #[case] sample_format: hound::SampleFormat,
+        #[case] max: i32,
+    ) {
+        let format = WavFormat::new(1, 8000, bit_depth);
+        let bytes = encode_wav(&format, &[1.0, -1.0, 2.0, -2.0]).unwrap();
+
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, bit_depth.bits_per_sample());
+        assert_eq!(reader.spec().sample_format, sample_format);
+        let samples: Vec<i32> = reader.samples().map(|sample| sample.unwrap()).collect();
+        // 範囲外の値はクリップされる
+        assert_eq!(samples, vec![max, -max, max, -max - 1]);
+    }
+
+    #[test]
+    fn test_encode_wav_float() {
+        let format = WavFormat::new(1, 8000, BitDepth::Float32);
+        let bytes = encode_wav(&format, &[0.0, 0.25, -1.0]).unwrap();
+
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+        let samples: Vec<f32> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(samples, vec![0.0, 0.25, -1.0]);
+    }
+
+    #[test]
+    fn test_quantize_dither() {
+        let samples = vec![0.3 / i16::MAX as f64; 1000];
+        let plain: Vec<i32> = quantize(samples.iter().copied(), 16, false).collect();
+        let dithered: Vec<i32> = quantize(samples.iter().copied(), 16, true).collect();
+
+        assert!(plain.iter().all(|&sample| sample == 0));
+        assert!(dithered.iter().all(|sample| (-1..=1).contains(sample)));
+        assert!(dithered.iter().any(|&sample| sample != 0));
+        // 平均すると元の値に近づく
+        let mean = dithered.iter().sum::<i32>() as f64 / dithered.len() as f64;
+        assert!((mean - 0.3).abs() < 0.1);
+        assert_eq!(
+            dithered,
+            quantize(samples.iter().copied(), 16, true).collect::<Vec<i32>>()
+        );
+    }
+
+    #[test]
+    fn test_chunk() {
+        let cases: Vec<(&[u8], Vec<u8>)> = vec![
+            (b"ab", b"test\x02\x00\x00\x00ab".to_vec()),
+            (b"abc", b"test\x03\x00\x00\x00abc\x00".to_vec()),
+        ];
+
+        for (data, expected) in cases {
+            assert_eq!(chunk(b"test", data), expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_wav_with_metadata() {
+        let format = WavFormat::new(1, 8000, BitDepth::Int24);
+        let metadata = WavMetadata {
+            title: "吾輩は猫である".to_string(),
+            comment: "sample_rate=8000".to_string(),
+            cues: vec![
+                Cue {
+                    frame: 0,
+                    label: "吾輩は猫である。".to_string(),
+                },
+                Cue {
+                    frame: 2,
+                    label: "名前はまだ無い。".to_string(),
+                },
+            ],
+        };
+        let bytes = encode_wav_with_metadata(&format, &[0.0, 0.5, -0.5], &metadata).unwrap();
+
+        let riff_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        assert_eq!(riff_size as usize, bytes.len() - 8);
+        assert!(bytes
+            .windows(12)
+            .any(|window| window == b"INAM\x16\x00\x00\x00\xE5\x90\xBE\xE8"));
+        assert!(bytes.windows(11).any(|window| window == b"data2sound\0"));
+        assert!(bytes
+            .windows(12)
+            .any(|window| window == b"cue \x34\x00\x00\x00\x02\x00\x00\x00"));
+        assert!(bytes.windows(4).any(|window| window == b"adtl"));
+
+        // 追加したチャンクがあっても音声データはそのまま読める
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        let samples: Vec<i32> = reader.samples().map(|sample| sample.unwrap()).collect();
+        assert_eq!(samples, vec![0, 4194304, -4194304]);
+    }
+
+    #[test]
+    fn test_write_wav_to_cursor() {
+        let mut cursor = Cursor::new(vec![]);
+        let format = WavFormat::new(2, 44100, BitDepth::Int16);
+        write_wav(&format, [0.1, 0.2, 0.3, 0.4], &mut cursor).unwrap();
+
+        cursor.set_position(0);
+        let reader = hound::WavReader::new(cursor).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.duration(), 2);
+    }
+
+    #[test]
+    fn test_export_wav_invalid_path() {
+        let path = Path::new("/nonexistent/data2sound/sine.wav");
+        let result = export_wav(&WavFormat::new(1, 8000, BitDepth::Int16), &[0.0], path);
+
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_export_wav_unfinished_frame() {
+        let path = std::env::temp_dir().join("data2sound_test_export_wav_unfinished_frame.wav");
+        // ステレオなのにサンプル数が奇数
+        let result = export_wav(&WavFormat::new(2, 8000, BitDepth::Int16), &[0.0], &path);
+
+        assert!(matches!(result, Err(Error::Wav(_))));
+        let _ = std::fs::remove_file(path);
     }
 }
@@ -0,0 +1,473 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    domain::{
+        category::Category,
+        pitch::midi_to_frequency,
+        sampler::{Sample, SampleZone, Sampler},
+    },
+    error::{Error, Result},
+};
+
+const GEN_START_ADDRS_OFFSET: u16 = 0;
+const GEN_END_ADDRS_OFFSET: u16 = 1;
+const GEN_INSTRUMENT: u16 = 41;
+const GEN_KEY_RANGE: u16 = 43;
+const GEN_COARSE_TUNE: u16 = 51;
+const GEN_FINE_TUNE: u16 = 52;
+const GEN_SAMPLE_ID: u16 = 53;
+const GEN_SAMPLE_MODES: u16 = 54;
+const GEN_OVERRIDING_ROOT_KEY: u16 = 58;
+
+pub struct SoundFont {
+    pub presets: Vec<Preset>,
+    instruments: Vec<Vec<Zone>>,
+    sample_headers: Vec<SampleHeader>,
+    samples: Vec<f32>,
+}
+
+pub struct Preset {
+    pub name: String,
+    pub bank: u16,
+    pub program: u16,
+    zones: Vec<Zone>,
+}
+
+#[derive(Clone, Default)]
+struct Zone {
+    generators: HashMap<u16, [u8; 2]>,
+}
+
+struct SampleHeader {
+    start: usize,
+    end: usize,
+    start_loop: usize,
+    end_loop: usize,
+    sample_rate: u32,
+    original_pitch: u8,
+    pitch_correction: i8,
+}
+
+pub fn import_sf2(path: &Path) -> Result<SoundFont> {
+    SoundFont::parse(&std::fs::read(path)?)
+}
+
+impl SoundFont {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let riff =
+            find_chunk(bytes, b"RIFF").ok_or(Error::InvalidSoundFont("missing RIFF header"))?;
+        if riff.get(..4) != Some(b"sfbk") {
+            return Err(Error::InvalidSoundFont("not a SoundFont 2 file"));
+        }
+        let sdta =
+            find_list(&riff[4..], b"sdta").ok_or(Error::InvalidSoundFont("missing sdta list"))?;
+        let pdta =
+            find_list(&riff[4..], b"pdta").ok_or(Error::InvalidSoundFont("missing pdta list"))?;
+
+        let samples = find_chunk(sdta, b"smpl")
+            .ok_or(Error::InvalidSoundFont("missing smpl chunk"))?
+            .chunks_exact(2)
+            .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0)
+            .collect();
+
+        let pdta_chunk = |id: &[u8; 4], size: usize| -> Result<Vec<&[u8]>> {
+            let chunk =
+                find_chunk(pdta, id).ok_or(Error::InvalidSoundFont("missing pdta chunk"))?;
+            Ok(chunk.chunks_exact(size).collect())
+        };
+        let phdr = pdta_chunk(b"phdr", 38)?;
+        let pbag = pdta_chunk(b"pbag", 4)?;
+        let pgen = pdta_chunk(b"pgen", 4)?;
+        let inst = pdta_chunk(b"inst", 22)?;
+        let ibag = pdta_chunk(b"ibag", 4)?;
+        let igen = pdta_chunk(b"igen", 4)?;
+        let shdr = pdta_chunk(b"shdr", 46)?;
+
+        // 各リストの最後のレコードは終端を示すダミー
+        let presets = phdr
+            .windows(2)
+            .map(|records| Preset {
+                name: read_name(records[0]),
+                program: read_u16(records[0], 20),
+                bank: read_u16(records[0], 22),
+                zones: read_zones(
+                    read_u16(records[0], 24) as usize..read_u16(records[1], 24) as usize,
+                    &pbag,
+                    &pgen,
+                ),
+            })
+            .collect();
+        let instruments = inst
+            .windows(2)
+            .map(|records| {
+                read_zones(
+                    read_u16(records[0], 20) as usize..read_u16(records[1], 20) as usize,
+                    &ibag,
+                    &igen,
+                )
+            })
+            .collect();
+        let sample_headers = shdr
+            .iter()
+            .map(|record| SampleHeader {
+                start: read_u32(record, 20) as usize,
+                end: read_u32(record, 24) as usize,
+                start_loop: read_u32(record, 28) as usize,
+                end_loop: read_u32(record, 32) as usize,
+                sample_rate: read_u32(record, 36),
+                original_pitch: record[40],
+                pitch_correction: record[41] as i8,
+            })
+            .collect();
+
+        Ok(Self {
+            presets,
+            instruments,
+            sample_headers,
+            samples,
+        })
+    }
+
+    pub fn sampler(&self, bank: u16, program: u16) -> Option<Sampler> {
+        let preset = self
+            .presets
+            .iter()
+            .find(|preset| preset.bank == bank && preset.program == program)?;
+
+        let mut zones = vec![];
+        for preset_zone in with_global(&preset.zones, GEN_INSTRUMENT) {
+            let Some(instrument) = preset_zone
+                .get(GEN_INSTRUMENT)
+                .and_then(|index| self.instruments.get(index as usize))
+            else {
+                continue;
+            };
+
+            for instrument_zone in with_global(instrument, GEN_SAMPLE_ID) {
+                let Some(header) = instrument_zone
+                    .get(GEN_SAMPLE_ID)
+                    .and_then(|index| self.sample_headers.get(index as usize))
+                else {
+                    continue;
+                };
+                let (preset_low, preset_high) = preset_zone.key_range();
+                let (instrument_low, instrument_high) = instrument_zone.key_range();
+                let (low_key, high_key) = (
+                    preset_low.max(instrument_low),
+                    preset_high.min(instrument_high),
+                );
+                if low_key > high_key {
+                    continue;
+                }
+                if let Some(sample) = self.read_sample(&preset_zone, &instrument_zone, header) {
+                    zones.push(SampleZone {
+                        sample,
+                        low_key,
+                        high_key,
+                    });
+                }
+            }
+        }
+
+        if zones.is_empty() {
+            None
+        } else {
+            Some(Sampler::new(zones))
+        }
+    }
+
+    pub fn sampler_for_category(&self, category: Category) -> Option<Sampler> {
+        self.sampler(0, category.program() as u16)
+    }
+
+    fn read_sample(
+        &self,
+        preset_zone: &Zone,
+        instrument_zone: &Zone,
+        header: &SampleHeader,
+    ) -> Option<Sample> {
+        let start = offset(
+            header.start,
+            instrument_zone.get_signed(GEN_START_ADDRS_OFFSET),
+        );
+        let end = offset(header.end, instrument_zone.get_signed(GEN_END_ADDRS_OFFSET));
+        let frames = self.samples.get(start..end)?.to_vec();
+
+        // sampleModes: 1 = 常にループ, 3 = リリースまでループ
+        let loop_points = match instrument_zone.get(GEN_SAMPLE_MODES).unwrap_or(0) & 3 {
+            1 | 3 if header.start_loop >= start && header.end_loop <= end => {
+                Some((header.start_loop - start, header.end_loop - start))
+            }
+            _ => None,
+        };
+
+        let root_key = match instrument_zone.get_signed(GEN_OVERRIDING_ROOT_KEY) {
+            Some(key @ 0..=127) => key as f32,
+            _ if header.original_pitch <= 127 => header.original_pitch as f32,
+            _ => 60.0,
+        };
+        let tune = [preset_zone, instrument_zone]
+            .iter()
+            .map(|zone| {
+                zone.get_signed(GEN_COARSE_TUNE).unwrap_or(0) as f32
+                    + zone.get_signed(GEN_FINE_TUNE).unwrap_or(0) as f32 / 100.0
+            })
+            .sum::<f32>()
+            + header.pitch_correction as f32 / 100.0;
+
+        Some(Sample {
+            frames,
+            sample_rate: header.sample_rate,
+            root_frequency: midi_to_frequency(root_key - tune),
+            loop_points,
+        })
+    }
+}
+
+impl Zone {
+    fn get(&self, generator: u16) -> Option<u16> {
+        self.generators
+            .get(&generator)
+            .map(|amount| u16::from_le_bytes(*amount))
+    }
+
+    fn get_signed(&self, generator: u16) -> Option<i16> {
+        self.generators
+            .get(&generator)
+            .map(|amount| i16::from_le_bytes(*amount))
+    }
+
+    fn key_range(&self) -> (u8, u8) {
+        self.generators
+            .get(&GEN_KEY_RANGE)
+            .map(|amount| (amount[0], amount[1]))
+            .unwrap_or((0, 127))
+    }
+}
+
+// 先頭のグローバルゾーンの設定を各ゾーンの既定値として適用する
+fn with_global(zones: &[Zone], terminal_generator: u16) -> Vec<Zone> {
+    match zones.split_first() {
+        Some((first, rest)) if !first.generators.contains_key(&terminal_generator) => rest
+            .iter()
+            .map(|zone| {
+                let mut merged = first.clone();
+                merged.generators.extend(zone.generators.clone());
+                merged
+            })
+            .collect(),
+        _ => zones.to_vec(),
+    }
+}
+
+fn read_zones(
+    bags: std::ops::Range<usize>,
+    bag_records: &[&[u8]],
+    gen_records: &[&[u8]],
+) -> Vec<Zone> {
+    bags.filter_map(|bag| {
+        let start = read_u16(bag_records.get(bag)?, 0) as usize;
+        let end = read_u16(bag_records.get(bag + 1)?, 0) as usize;
+        let generators = gen_records
+            .get(start..end)?
+            .iter()
+            .map(|record| (read_u16(record, 0), [record[2], record[3]]))
+            .collect();
+        Some(Zone { generators })
+    })
+    .collect()
+}
+
+fn find_chunk<'a>(bytes: &'a [u8], id: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut position = 0;
+    while position + 8 <= bytes.len() {
+        let size = read_u32(bytes, position + 4) as usize;
+        let body = bytes.get(position + 8..position + 8 + size)?;
+        if &bytes[position..position + 4] == id {
+            return Some(body);
+        }
+        // チャンクは偶数バイト境界に揃えられている
+        position += 8 + size + size % 2;
+    }
+    None
+}
+
+fn find_list<'a>(bytes: &'a [u8], list_type: &[u8; 4]) -> Option<&'a [u8]> {
+    let mut position = 0;
+    while position + 8 <= bytes.len() {
+        let size = read_u32(bytes, position + 4) as usize;
+        let body = bytes.get(position + 8..position + 8 + size)?;
+        if &bytes[position..position + 4] == b"LIST" && body.get(..4) == Some(list_type) {
+            return Some(&body[4..]);
+        }
+        position += 8 + size + size % 2;
+    }
+    None
+}
+
+fn read_name(record: &[u8]) -> String {
+    let name = &record[..20];
+    let end = name
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
+
+fn read_u16(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes([bytes[position], bytes[position + 1]])
+}
+
+fn read_u32(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes(bytes[position..position + 4].try_into().unwrap())
+}
+
+fn offset(position: usize, offset: Option<i16>) -> usize {
+    (position as i64 + offset.unwrap_or(0) as i64).max(0) as usize
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend((body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        if body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = list_type.to_vec();
+        body.extend(chunks.concat());
+        chunk(b"LIST", &body)
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(20, 0);
+        bytes
+    }
+
+    fn generator(operator: u16, amount: [u8; 2]) -> Vec<u8> {
+        let mut bytes = operator.to_le_bytes().to_vec();
+        bytes.extend(amount);
+        bytes
+    }
+
+    fn sample_header(sample_name: &str, start: u32, end: u32, pitch: u8) -> Vec<u8> {
+        let mut bytes = name(sample_name);
+        for value in [start, end, start + 2, end.saturating_sub(2), 22050] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([pitch, 0, 0, 0, 1, 0]);
+        bytes
+    }
+
+    // プリセット1つ、2つのキー範囲に分かれた楽器1つを含む最小限のSoundFont
+    pub(crate) fn build_sound_font() -> Vec<u8> {
+        let smpl: Vec<u8> = (0..16_i16).flat_map(|i| (i * 1000).to_le_bytes()).collect();
+
+        let mut phdr = name("Strings");
+        phdr.extend([48, 0, 0, 0, 0, 0]);
+        phdr.extend([0; 12]);
+        phdr.extend(name("EOP"));
+        phdr.extend([0, 0, 0, 0, 1, 0]);
+        phdr.extend([0; 12]);
+        let pbag = [[0, 0, 0, 0], [1, 0, 0, 0]].concat();
+        let pgen = [generator(GEN_INSTRUMENT, [0, 0]), generator(0, [0, 0])].concat();
+
+        let inst = [name("Strings"), vec![0, 0], name("EOI"), vec![3, 0]].concat();
+        let ibag = [[0, 0, 0, 0], [1, 0, 0, 0], [4, 0, 0, 0], [7, 0, 0, 0]].concat();
+        let igen = [
+            generator(GEN_SAMPLE_MODES, [1, 0]),
+            generator(GEN_KEY_RANGE, [0, 59]),
+            generator(GEN_OVERRIDING_ROOT_KEY, [57, 0]),
+            generator(GEN_SAMPLE_ID, [0, 0]),
+            generator(GEN_KEY_RANGE, [60, 127]),
+            generator(GEN_COARSE_TUNE, [12, 0]),
+            generator(GEN_SAMPLE_ID, [1, 0]),
+            generator(0, [0, 0]),
+        ]
+        .concat();
+        let shdr = [
+            sample_header("Low", 0, 8, 60),
+            sample_header("High", 8, 16, 72),
+            sample_header("EOS", 0, 0, 0),
+        ]
+        .concat();
+
+        let mut riff = b"sfbk".to_vec();
+        riff.extend(list(b"INFO", &[chunk(b"ifil", &[2, 0, 1, 0])]));
+        riff.extend(list(b"sdta", &[chunk(b"smpl", &smpl)]));
+        riff.extend(list(
+            b"pdta",
+            &[
+                chunk(b"phdr", &phdr),
+                chunk(b"pbag", &pbag),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &pgen),
+                chunk(b"inst", &inst),
+                chunk(b"ibag", &ibag),
+                chunk(b"imod", &[0; 10]),
+                chunk(b"igen", &igen),
+                chunk(b"shdr", &shdr),
+            ],
+        ));
+        chunk(b"RIFF", &riff)
+    }
+
+    #[test]
+    fn test_parse_presets() {
+        let sound_font = SoundFont::parse(&build_sound_font()).unwrap();
+
+        assert_eq!(sound_font.presets.len(), 1);
+        assert_eq!(sound_font.presets[0].name, "Strings");
+        assert_eq!(sound_font.presets[0].program, 48);
+        assert_eq!(sound_font.presets[0].bank, 0);
+    }
+
+    #[test]
+    fn test_sampler() {
+        let sound_font = SoundFont::parse(&build_sound_font()).unwrap();
+        let sampler = sound_font.sampler(0, 48).unwrap();
+
+        assert_eq!(sampler.zones.len(), 2);
+
+        let low = &sampler.zones[0];
+        assert_eq!((low.low_key, low.high_key), (0, 59));
+        assert!((low.sample.root_frequency - 220.0).abs() < 0.01);
+        assert_eq!(low.sample.loop_points, Some((2, 6)));
+        assert_eq!(low.sample.sample_rate, 22050);
+        assert_eq!(low.sample.frames.len(), 8);
+
+        let high = &sampler.zones[1];
+        assert_eq!((high.low_key, high.high_key), (60, 127));
+        assert!((high.sample.root_frequency - 261.63).abs() < 0.01);
+        assert_eq!(high.sample.loop_points, Some((2, 6)));
+        assert!((high.sample.frames[0] - 8000.0 / 32768.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sampler_for_category() {
+        let sound_font = SoundFont::parse(&build_sound_font()).unwrap();
+
+        assert!(sound_font.sampler_for_category(Category::History).is_some());
+        assert!(sound_font.sampler_for_category(Category::Sports).is_none());
+    }
+
+    #[test]
+    fn test_sampler_unknown_preset() {
+        let sound_font = SoundFont::parse(&build_sound_font()).unwrap();
+        assert!(sound_font.sampler(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(SoundFont::parse(b"RIFF\x04\x00\x00\x00WAVE").is_err());
+        assert!(SoundFont::parse(b"").is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use crate::{domain::sampler::Sample, error::Result};
+
+pub fn import_wav(path: &Path, root_frequency: f32) -> Result<Sample> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<hound::Result<_>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / scale))
+                .collect::<hound::Result<_>>()?
+        }
+    };
+
+    // 多チャンネルのサンプルはモノラルにダウンミックスする
+    let frames = interleaved
+        .chunks(spec.channels as usize)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    Ok(Sample {
+        frames,
+        sample_rate: spec.sample_rate,
+        root_frequency,
+        loop_points: None,
+    })
+}
@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use rodio::{buffer::SamplesBuffer, OutputStream, Sink, Source};
+
+use crate::error::Result;
+
+// 進捗を通知する間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Player {
+    sink: Sink,
+    // ストリームを破棄すると再生が止まるので保持しておく
+    _stream: Option<OutputStream>,
+}
+
+impl Player {
+    pub fn new() -> Result<Self> {
+        let (stream, handle) = OutputStream::try_default()?;
+        let sink = Sink::try_new(&handle)?;
+        Ok(Self {
+            sink,
+            _stream: Some(stream),
+        })
+    }
+
+    // 出力先を自分で用意する場合（Sink::new_idle など）
+    pub fn with_sink(sink: Sink) -> Self {
+        Self {
+            sink,
+            _stream: None,
+        }
+    }
+
+    pub fn play_samples(
+        &self,
+        channels: u16,
+        sample_rate: u32,
+        samples: &[f64],
+        on_progress: impl FnMut(Duration) + Send + 'static,
+    ) {
+        let samples: Vec<f32> = samples.iter().map(|&sample| sample as f32).collect();
+        self.play_source(
+            SamplesBuffer::new(channels, sample_rate, samples),
+            on_progress,
+        );
+    }
+
+    // Renderer など、その場で生成されるサンプル列を再生する
+    pub fn play_stream<I>(
+        &self,
+        channels: u16,
+        sample_rate: u32,
+        samples: I,
+        on_progress: impl FnMut(Duration) + Send + 'static,
+    ) where
+        I: Iterator<Item = f64> + Send + 'static,
+    {
+        self.play_source(
+            Stream {
+                samples,
+                channels,
+                sample_rate,
+            },
+            on_progress,
+        );
+    }
+
+    pub fn play_source<S>(&self, source: S, on_progress: impl FnMut(Duration) + Send + 'static)
+    where
+        S: Source<Item = f32> + Send + 'static,
+    {
+        self.sink.append(Progress::new(source, on_progress));
+        self.sink.play();
+    }
+
+    pub fn play(&self) {
+        self.sink.play();
+    }
+
+    pub fn pause(&self) {
+        self.sink.pause();
+    }
+
+    pub fn stop(&self) {
+        self.sink.stop();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.sink.empty()
+    }
+
+    pub fn position(&self) -> Duration {
+        self.sink.get_pos()
+    }
+
+    pub fn wait(&self) {
+        self.sink.sleep_until_end();
+    }
+}
+
+struct Stream<I> {
+    samples: I,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl<I: Iterator<Item = f64>> Iterator for Stream<I> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.samples.next().map(|sample| sample as f32)
+    }
+}
+
+impl<I: Iterator<Item = f64>> Source for Stream<I> {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// 再生したサンプル数を数え、一定間隔と終了時に経過時間を通知する
+struct Progress<S, F> {
+    source: S,
+    on_progress: F,
+    played: u64,
+    interval: u64,
+    finished: bool,
+}
+
+impl<S: Source<Item = f32>, F: FnMut(Duration)> Progress<S, F> {
+    fn new(source: S, on_progress: F) -> Self {
+        let samples_per_second = source.sample_rate() as u64 * source.channels() as u64;
+        let interval = (samples_per_second * PROGRESS_INTERVAL.as_millis() as u64 / 1000).max(1);
+        Self {
+            source,
+            on_progress,
+            played: 0,
+            interval,
+            finished: false,
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        let frames = self.played / self.source.channels() as u64;
+        Duration::from_secs_f64(frames as f64 / self.source.sample_rate() as f64)
+    }
+}
+
+impl<S: Source<Item = f32>, F: FnMut(Duration)> Iterator for Progress<S, F> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        match self.source.next() {
+            Some(sample) => {
+                self.played += 1;
+                if self.played.is_multiple_of(self.interval) {
+                    let elapsed = self.elapsed();
+                    (self.on_progress)(elapsed);
+                }
+                Some(sample)
+            }
+            None => {
+                if !self.finished {
+                    self.finished = true;
+                    let elapsed = self.elapsed();
+                    (self.on_progress)(elapsed);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<S: Source<Item = f32>, F: FnMut(Duration)> Source for Progress<S, F> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{instrument::Sine, pure_tone::ToneAndDuration, renderer::Renderer};
+    use std::sync::{Arc, Mutex};
+
+    fn recorder() -> (
+        Arc<Mutex<Vec<Duration>>>,
+        impl FnMut(Duration) + Send + 'static,
+    ) {
+        let progress = Arc::new(Mutex::new(vec![]));
+        let sender = progress.clone();
+        (progress, move |elapsed| {
+            sender.lock().unwrap().push(elapsed)
+        })
+    }
+
+    #[test]
+    fn test_progress() {
+        let source = SamplesBuffer::new(2, 100, vec![0.0_f32; 100]);
+        let (progress, on_progress) = recorder();
+        let samples: Vec<f32> = Progress::new(source, on_progress).collect();
+
+        assert_eq!(samples.len(), 100);
+        // 100ms ごとに1回と、終了時に1回
+        assert_eq!(
+            *progress.lock().unwrap(),
+            (1..=5)
+                .map(|i| Duration::from_millis(i * 100))
+                .chain([Duration::from_millis(500)])
+                .collect::<Vec<Duration>>()
+        );
+    }
+
+    #[test]
+    fn test_play_samples() {
+        let (sink, output) = Sink::new_idle();
+        let player = Player::with_sink(sink);
+        let (progress, on_progress) = recorder();
+        player.play_samples(1, 1000, &[0.5; 1000], on_progress);
+
+        let played: Vec<f32> = output.take(1000).collect();
+        assert!(played.iter().all(|&sample| sample == 0.5));
+        assert_eq!(progress.lock().unwrap().len(), 10);
+        assert_eq!(
+            progress.lock().unwrap().last(),
+            Some(&Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_play_stream() {
+        let (sink, output) = Sink::new_idle();
+        let player = Player::with_sink(sink);
+        let track = vec![ToneAndDuration {
+            frequency: 100.0,
+            duration: 1.0,
+        }];
+        let (progress, on_progress) = recorder();
+        player.play_stream(1, 1000, Renderer::new(1000, track, Sine), on_progress);
+
+        let played: Vec<f32> = output.take(1000).collect();
+        assert!(played.iter().any(|&sample| sample > 0.5));
+        assert_eq!(
+            progress.lock().unwrap().last(),
+            Some(&Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let (sink, mut output) = Sink::new_idle();
+        let player = Player::with_sink(sink);
+        let (progress, on_progress) = recorder();
+        player.play_samples(1, 1000, &[0.5; 1000], on_progress);
+
+        output.by_ref().take(200).for_each(drop);
+        player.pause();
+        assert!(player.is_paused());
+        // 一時停止が反映されるまで最大 5ms かかる
+        output.by_ref().take(10).for_each(drop);
+        let reported = progress.lock().unwrap().len();
+        let silence: Vec<f32> = output.by_ref().take(500).collect();
+        assert!(silence.iter().all(|&sample| sample == 0.0));
+        assert_eq!(progress.lock().unwrap().len(), reported);
+
+        player.play();
+        output.by_ref().take(1000).for_each(drop);
+        assert_eq!(
+            progress.lock().unwrap().last(),
+            Some(&Duration::from_secs(1))
+        );
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn test_stop() {
+        let (sink, mut output) = Sink::new_idle();
+        let player = Player::with_sink(sink);
+        player.play_samples(1, 1000, &[0.5; 1000], |_| {});
+
+        output.by_ref().take(100).for_each(drop);
+        player.stop();
+        output.by_ref().take(10).for_each(drop);
+
+        assert!(output.take(100).all(|sample| sample == 0.0));
+        assert!(player.is_finished());
+    }
+}
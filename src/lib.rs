@@ -1,25 +1,135 @@
-use std::{path::Path, vec};
-
-use domain::{
-    pure_tone::{PureTones, ToneAndDuration},
-    text2track::Text2Track,
-    text_analyzer::TextAnalyzer,
-};
-
-mod domain;
-mod infrastructure;
-use infrastructure::export_wav::export_wav;
+pub mod application;
+pub mod domain;
+pub mod error;
+pub mod infrastructure;
 
 mod utils;
 
 use wasm_bindgen::prelude::*;
 
+use application::{config::GenerationConfig, TextRenderer};
+use infrastructure::export_wav::BitDepth;
+
+// wasm モジュールの読み込み時に一度だけ呼ばれる
+#[wasm_bindgen(start)]
+pub fn start() {
+    utils::set_panic_hook();
+}
+
+#[wasm_bindgen]
+pub fn text_to_samples(text: &str, sample_rate: u32) -> Result<Vec<f32>, JsError> {
+    let config = GenerationConfig {
+        sample_rate,
+        ..Default::default()
+    };
+    Ok(application::text_to_samples(text, &config)
+        .map_err(js_error)?
+        .into_iter()
+        .map(|sample| sample as f32)
+        .collect())
+}
+
+#[wasm_bindgen]
+pub fn text_to_wav(text: &str) -> Result<Vec<u8>, JsError> {
+    application::text_to_wav(text, &GenerationConfig::default(), BitDepth::Int16).map_err(js_error)
+}
+
+// config は GenerationConfig の JSON
+#[wasm_bindgen]
+pub fn text_to_samples_with_config(text: &str, config: &str) -> Result<Vec<f32>, JsError> {
+    let config = GenerationConfig::from_json(config).map_err(js_error)?;
+    Ok(application::text_to_samples(text, &config)
+        .map_err(js_error)?
+        .into_iter()
+        .map(|sample| sample as f32)
+        .collect())
+}
+
+#[wasm_bindgen]
+pub fn text_to_wav_with_config(text: &str, config: &str) -> Result<Vec<u8>, JsError> {
+    let config = GenerationConfig::from_json(config).map_err(js_error)?;
+    application::text_to_wav(text, &config, BitDepth::Int16).map_err(js_error)
+}
+
+fn js_error(error: error::Error) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+// AudioWorklet などから少しずつ取り出せるよう、曲を分割して描画する
 #[wasm_bindgen]
-extern "C" {
-    fn alert(s: &str);
+pub struct StreamingRenderer {
+    renderer: TextRenderer,
 }
 
 #[wasm_bindgen]
-pub fn greet() {
-    alert("Hello, wasm-game-of-life!");
+impl StreamingRenderer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str, sample_rate: u32) -> Result<StreamingRenderer, JsError> {
+        let config = GenerationConfig {
+            sample_rate,
+            ..Default::default()
+        };
+        Ok(Self {
+            renderer: application::text_to_renderer(text, &config).map_err(js_error)?,
+        })
+    }
+
+    pub fn with_config(text: &str, config: &str) -> Result<StreamingRenderer, JsError> {
+        let config = GenerationConfig::from_json(config).map_err(js_error)?;
+        Ok(Self {
+            renderer: application::text_to_renderer(text, &config).map_err(js_error)?,
+        })
+    }
+
+    // 曲の終わりでは frames より短く、終わったあとは空になる
+    pub fn next_chunk(&mut self, frames: usize) -> Vec<f32> {
+        self.renderer
+            .by_ref()
+            .take(frames)
+            .map(|sample| sample as f32)
+            .collect()
+    }
+
+    pub fn remaining_frames(&self) -> usize {
+        self.renderer.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.renderer.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_renderer() {
+        let mut renderer = StreamingRenderer::new("こんにちは", 8000).unwrap();
+        assert_eq!(renderer.remaining_frames(), 16 * 8000);
+
+        let mut chunks = vec![];
+        while !renderer.is_finished() {
+            chunks.push(renderer.next_chunk(3000));
+        }
+
+        assert_eq!(chunks.len(), 43);
+        assert_eq!(chunks.last().unwrap().len(), 2000);
+        assert_eq!(
+            chunks.concat(),
+            text_to_samples("こんにちは", 8000).unwrap()
+        );
+        assert!(renderer.next_chunk(128).is_empty());
+    }
+
+    #[test]
+    fn test_with_config() {
+        let config = r#"{"sample_rate":8000,"effects":[{"type":"gain","db":-6.0}]}"#;
+        let mut renderer = StreamingRenderer::with_config("こんにちは", config).unwrap();
+
+        assert_eq!(
+            renderer.next_chunk(16 * 8000),
+            text_to_samples_with_config("こんにちは", config).unwrap()
+        );
+    }
 }
@@ -1,22 +1,303 @@
-use std::{path::Path, vec};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
 
-use domain::{
-    pure_tone::{PureTones, ToneAndDuration},
-    text2track::Text2Track,
-    text_analyzer::TextAnalyzer,
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use data2sound::{
+    application::{
+        batch::{read_inputs, run_batch, MANIFEST_FILE_NAME},
+        config::GenerationConfig,
+        export_text, text_to_renderer, OutputFormat,
+    },
+    domain::{humanize::Humanize, text2track::TrackType},
+    error::Result,
+    infrastructure::{export_wav::BitDepth, playback::Player},
 };
 
-mod domain;
-mod infrastructure;
-use infrastructure::export_wav::export_wav;
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Generate music from text",
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Convert a directory of *.txt files or a JSONL file of records")]
+    Batch(BatchArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[arg(help = "Text to convert. Read from --input or stdin when omitted")]
+    text: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "text",
+        help = "Read the text from a file (- for stdin)"
+    )]
+    input: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Output path. Defaults to sine.<ext>, or nothing with --play only"
+    )]
+    output: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "Guessed from the output extension when omitted"
+    )]
+    format: Option<Format>,
+    #[command(flatten)]
+    options: GenerationOptions,
+    #[arg(long, help = "Play the generated track")]
+    play: bool,
+}
+
+#[derive(Args)]
+struct BatchArgs {
+    #[arg(
+        help = "Directory of *.txt files, or a JSONL file of {\"id\", \"text\", \"config\"} records"
+    )]
+    input: PathBuf,
+    #[arg(short, long, help = "Directory for the outputs and the manifest")]
+    output_dir: PathBuf,
+    #[arg(short, long, value_enum, default_value = "wav")]
+    format: Format,
+    #[command(flatten)]
+    options: GenerationOptions,
+}
+
+#[derive(Args)]
+struct GenerationOptions {
+    #[arg(
+        short,
+        long,
+        help = "GenerationConfig JSON file, overridden by the options below"
+    )]
+    config: Option<PathBuf>,
+    #[arg(short = 'r', long)]
+    sample_rate: Option<u32>,
+    #[arg(short, long, value_enum, default_value = "16")]
+    bit_depth: BitDepthArg,
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Use this style instead of the one detected from the text"
+    )]
+    style: Option<Style>,
+    #[arg(
+        long,
+        help = "Humanize timing, velocity and melody (defaults unless set in --config)"
+    )]
+    humanize: bool,
+    #[arg(
+        long,
+        help = "SoundFont 2 file. Plays the preset for the category detected from the text"
+    )]
+    soundfont: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Wav,
+    Mid,
+    Flac,
+}
+
+impl Format {
+    // 出力先の拡張子から推測する
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "wav" => Some(Format::Wav),
+            "mid" | "midi" => Some(Format::Mid),
+            "flac" => Some(Format::Flac),
+            _ => None,
+        }
+    }
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Wav => OutputFormat::Wav,
+            Format::Mid => OutputFormat::Midi,
+            Format::Flac => OutputFormat::Flac,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BitDepthArg {
+    #[value(name = "16")]
+    Int16,
+    #[value(name = "24")]
+    Int24,
+    #[value(name = "32")]
+    Int32,
+    #[value(name = "32f")]
+    Float32,
+}
+
+impl From<BitDepthArg> for BitDepth {
+    fn from(bit_depth: BitDepthArg) -> Self {
+        match bit_depth {
+            BitDepthArg::Int16 => BitDepth::Int16,
+            BitDepthArg::Int24 => BitDepth::Int24,
+            BitDepthArg::Int32 => BitDepth::Int32,
+            BitDepthArg::Float32 => BitDepth::Float32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Style {
+    Hiragana,
+    Katakana,
+    Kanji,
+    Alphabets,
+}
+
+impl From<Style> for TrackType {
+    fn from(style: Style) -> Self {
+        match style {
+            Style::Hiragana => TrackType::Hiragana,
+            Style::Katakana => TrackType::Katakana,
+            Style::Kanji => TrackType::Kanji,
+            Style::Alphabets => TrackType::Alphabets,
+        }
+    }
+}
 
 fn main() {
-    let sample_rate = 44100;
-    let input = "Why Japanese people!?".into();
-    let text_analyzer = TextAnalyzer::new(input);
-    let text2track = Text2Track::new(text_analyzer);
-    let track = text2track.generate_track();
-    let s = PureTones::new(sample_rate, track);
-
-    export_wav(1, sample_rate, 16, s.samples, Path::new("sine.wav"));
+    // 引数の誤りは clap が終了コード 2 で終了させる
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Some(Command::Batch(args)) => batch(args),
+        None => generate(&cli.generate),
+    };
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+fn generate(args: &GenerateArgs) -> Result<()> {
+    let text = read_text(args)?;
+    let config = config(&args.options)?;
+
+    let format: OutputFormat = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Wav)
+        .into();
+    let output = match &args.output {
+        Some(output) => Some(output.clone()),
+        None if args.play => None,
+        None => Some(PathBuf::from(format!("sine.{}", format.extension()))),
+    };
+
+    if let Some(output) = output {
+        export_text(
+            &text,
+            &config,
+            format,
+            args.options.bit_depth.into(),
+            &output,
+        )?;
+    }
+
+    if args.play {
+        let player = Player::new()?;
+        player.play_stream(
+            1,
+            config.sample_rate,
+            text_to_renderer(&text, &config)?,
+            |_| {},
+        );
+        player.wait();
+    }
+    Ok(())
+}
+
+fn batch(args: &BatchArgs) -> Result<()> {
+    let inputs = read_inputs(&args.input)?;
+    let config = config(&args.options)?;
+    let entries = run_batch(
+        &inputs,
+        &config,
+        args.format.into(),
+        args.options.bit_depth.into(),
+        &args.output_dir,
+    )?;
+
+    let failed: Vec<_> = entries
+        .iter()
+        .filter(|entry| entry.error.is_some())
+        .collect();
+    for entry in &failed {
+        eprintln!("error: {}: {}", entry.id, entry.error.as_deref().unwrap());
+    }
+    println!(
+        "{} of {} converted, manifest written to {}",
+        entries.len() - failed.len(),
+        entries.len(),
+        args.output_dir.join(MANIFEST_FILE_NAME).display()
+    );
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn read_text(args: &GenerateArgs) -> Result<String> {
+    if let Some(text) = &args.text {
+        return Ok(text.clone());
+    }
+    match args.input.as_deref() {
+        Some(path) if path != Path::new("-") => Ok(std::fs::read_to_string(path)?),
+        _ => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn config(options: &GenerationOptions) -> Result<GenerationConfig> {
+    let mut config = match &options.config {
+        Some(path) => GenerationConfig::from_json(&std::fs::read_to_string(path)?)?,
+        None => GenerationConfig::default(),
+    };
+    if let Some(sample_rate) = options.sample_rate {
+        config.sample_rate = sample_rate;
+    }
+    if let Some(seed) = options.seed {
+        config.seed = Some(seed);
+    }
+    if let Some(style) = options.style {
+        config.style = Some(style.into());
+    }
+    if options.humanize && config.humanize.is_none() {
+        config.humanize = Some(Humanize::default());
+    }
+    if let Some(soundfont) = &options.soundfont {
+        config.soundfont = Some(soundfont.clone());
+    }
+    config.validate()?;
+    Ok(config)
 }